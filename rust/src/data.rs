@@ -1,4 +1,4 @@
-use cgmath::Vector2;
+use cgmath::{Vector2, vec2};
 
 pub type TilePoint = Vector2<i32>;
 pub type TileDelta = Vector2<i32>;
@@ -24,6 +24,77 @@ pub enum CellType {
     DefaultWall,
     RoomExit,
     Water,
+    Ice,
+}
+
+pub struct TerrainData {
+    // Blocks line of sight past this cell
+    pub blocks_sight: bool,
+    // Actors standing here can only be noticed from within this distance
+    pub concealment_range: Option<i32>,
+    pub slows: bool,
+    pub slippery: bool,
+    // What the look text says about the terrain, walls' sight blocking goes without saying
+    pub effects: &'static [&'static str],
+}
+
+const OPEN_TERRAIN: TerrainData = TerrainData {
+    blocks_sight: false,
+    concealment_range: None,
+    slows: false,
+    slippery: false,
+    effects: &[],
+};
+
+const SOLID_TERRAIN: TerrainData = TerrainData {
+    blocks_sight: true,
+    concealment_range: None,
+    slows: false,
+    slippery: false,
+    effects: &[],
+};
+
+const MOSS_TERRAIN: TerrainData = TerrainData {
+    blocks_sight: false,
+    concealment_range: Some(3),
+    slows: false,
+    slippery: false,
+    effects: &[ "muffles footsteps" ],
+};
+
+const THYME_TERRAIN: TerrainData = TerrainData {
+    blocks_sight: true,
+    concealment_range: Some(1),
+    slows: false,
+    slippery: false,
+    effects: &[ "blocks sight", "hides anyone standing in it" ],
+};
+
+const WATER_TERRAIN: TerrainData = TerrainData {
+    blocks_sight: false,
+    concealment_range: None,
+    slows: true,
+    slippery: false,
+    effects: &[ "slows anyone wading through it" ],
+};
+
+const ICE_TERRAIN: TerrainData = TerrainData {
+    blocks_sight: false,
+    concealment_range: None,
+    slows: false,
+    slippery: true,
+    effects: &[ "slippery" ],
+};
+
+pub fn get_terrain_data(cell_type: CellType) -> &'static TerrainData {
+    match cell_type {
+        CellType::Empty | CellType::DefaultFloor => &OPEN_TERRAIN,
+        CellType::OutOfBounds | CellType::DefaultWall | CellType::RoomExit => &SOLID_TERRAIN,
+        CellType::FloorMoss => &MOSS_TERRAIN,
+        CellType::FloorThyme => &THYME_TERRAIN,
+        CellType::Water => &WATER_TERRAIN,
+        CellType::Ice => &ICE_TERRAIN,
+    }
 }
 
 #[allow(unused)]
@@ -36,54 +107,336 @@ pub enum ActorType {
     ToothyStarling,
     DustySkeleton,
     BlueJelly,
+    BloodflowerBush,
+    BerryShrub,
+    Heron,
+    StarlightFairy,
+    Mermaid,
+}
+
+pub const ALL_ACTOR_TYPES: &[ActorType] = &[
+    ActorType::Player,
+    ActorType::Toad,
+    ActorType::MouseWarrior,
+    ActorType::MouseSkirmisher,
+    ActorType::ToothyStarling,
+    ActorType::DustySkeleton,
+    ActorType::BlueJelly,
+    ActorType::BloodflowerBush,
+    ActorType::BerryShrub,
+    ActorType::Heron,
+    ActorType::StarlightFairy,
+    ActorType::Mermaid,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Behavior {
+    // Runs from the player while fleeing, fighting back when cornered
+    Flee,
+    // Attacks the player when hunting them and adjacent
+    MeleeAttack,
+    // Throws a carried javelin when hunting and lined up with the player
+    ThrowJavelin,
+    // Heads for the player when hunting, or for wherever they were last noticed when searching
+    Pursue,
+    // Heads straight for the player when hunting, but gives up as soon as they're out of sight
+    Approach,
+    // Walks the pattern one step per action, looping
+    Patrol(&'static [TileDelta]),
+    // Walks straight ahead, turning clockwise at walls
+    TurnAtWalls,
+    // Walks in the direction given, turning back at walls
+    Pace(TileDelta),
+    // Dazzles the player from two tiles away in a straight line when hunting, then waits a few actions
+    InflictDazzle,
+    // Takes a random step with the given chance, otherwise stays put
+    Wander(f32),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BehaviorCondition {
+    Always,
+    PlayerWithin(i32),
+}
+
+pub struct BehaviorRule {
+    pub behavior: Behavior,
+    pub condition: BehaviorCondition,
+}
+
+const fn always(behavior: Behavior) -> BehaviorRule {
+    BehaviorRule { behavior, condition: BehaviorCondition::Always }
 }
 
+const TOAD_PATROL_PATTERN: &[TileDelta] = &[
+    vec2(1, 0),
+    vec2(0, 1),
+    vec2(-1, 0),
+    vec2(0, -1),
+];
+
+// ..*..
+// *.*.*
+// .....
+// .*.*.
+// Starlings are fast, so every three steps lands on a star
+const STARLING_PATROL_PATTERN: &[TileDelta] = &[
+    vec2(0, 1), vec2(1, 0), vec2(1, 0),
+    vec2(-1, 0), vec2(0, 1), vec2(0, 1),
+    vec2(0, -1), vec2(0, -1), vec2(-1, 0),
+    vec2(-1, 0), vec2(0, 1), vec2(0, 1),
+    vec2(0, -1), vec2(0, -1), vec2(-1, 0),
+    vec2(1, 0), vec2(1, 0), vec2(0, -1),
+];
+
 pub struct ActorBaseStats {
     pub max_hp: i32,
     pub attack_power: i32,
     pub defense_power: i32,
+    // How far away the player can be noticed, before the player's stealth is taken off
+    pub sight_range: i32,
+    pub stealth: i32,
+    // Energy gained per turn, see ACTION_COST
+    pub speed: i32,
+    // Energy spent on an unarmed attack
+    pub attack_cost: i32,
+    // Given to the player for the kill
+    pub experience: i32,
+    // Whether a carcass is left behind when the hunger clock is on
+    pub leaves_carcass: bool,
+    // Loose items of these categories are taken when the monster walks over them, only things it has a use for
+    pub picks_up: &'static [ItemCategory],
+    // Tried in order each action, the first one that applies is used. Monsters with none are passive.
+    pub behaviors: &'static [BehaviorRule],
+    // Swimmers aren't slowed by water
+    pub swims: bool,
+    // Monsters break and flee once their HP drops to this fraction of their max HP
+    pub flee_threshold: Option<f32>,
 }
 
 const PLAYER_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 24,
     attack_power: 2,
     defense_power: 0,
+    sight_range: 0,
+    stealth: 2,
+    speed: 100,
+    attack_cost: 100,
+    experience: 0,
+    leaves_carcass: false,
+    picks_up: &[],
+    behaviors: &[],
+    swims: false,
+    flee_threshold: None,
 };
 
 const TOAD_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 4,
     attack_power: 2,
     defense_power: 0,
+    sight_range: 4,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 2,
+    leaves_carcass: true,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::MeleeAttack),
+        always(Behavior::Patrol(TOAD_PATROL_PATTERN)),
+    ],
+    swims: false,
+    flee_threshold: None,
 };
 
 const MOUSE_WARRIOR_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 16,
     attack_power: 5,
     defense_power: 2,
+    sight_range: 7,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 8,
+    leaves_carcass: true,
+    picks_up: &[ ItemCategory::Weapon ],
+    behaviors: &[
+        always(Behavior::Flee),
+        always(Behavior::MeleeAttack),
+        always(Behavior::Pursue),
+    ],
+    swims: false,
+    flee_threshold: Some(0.25),
 };
 
 const MOUSE_SKIRMISHER_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 10,
     attack_power: 3,
     defense_power: 1,
+    sight_range: 8,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 5,
+    leaves_carcass: true,
+    picks_up: &[ ItemCategory::Weapon ],
+    behaviors: &[
+        always(Behavior::Flee),
+        always(Behavior::MeleeAttack),
+        always(Behavior::ThrowJavelin),
+        always(Behavior::Pursue),
+    ],
+    swims: false,
+    flee_threshold: Some(0.3),
 };
 
 const TOOTHY_STARLING_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 8,
     attack_power: 3,
     defense_power: 0,
+    sight_range: 6,
+    stealth: 0,
+    speed: 300,
+    attack_cost: 300,
+    experience: 5,
+    leaves_carcass: true,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::Flee),
+        always(Behavior::MeleeAttack),
+        always(Behavior::Patrol(STARLING_PATROL_PATTERN)),
+    ],
+    swims: false,
+    flee_threshold: Some(0.25),
 };
 
 const DUSTY_SKELETON_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 9,
     attack_power: 5,
     defense_power: 1,
+    sight_range: 5,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 6,
+    leaves_carcass: false,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::MeleeAttack),
+        always(Behavior::TurnAtWalls),
+    ],
+    swims: false,
+    flee_threshold: None,
 };
 
 const BLUE_JELLY_STATS: ActorBaseStats = ActorBaseStats {
     max_hp: 6,
     attack_power: 3,
     defense_power: 0,
+    sight_range: 4,
+    stealth: 0,
+    speed: 50,
+    attack_cost: 100,
+    experience: 3,
+    leaves_carcass: false,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::MeleeAttack),
+        always(Behavior::Pursue),
+    ],
+    swims: false,
+    flee_threshold: None,
+};
+
+const BLOODFLOWER_BUSH_STATS: ActorBaseStats = ActorBaseStats {
+    max_hp: 6,
+    attack_power: 0,
+    defense_power: 1,
+    sight_range: 0,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 0,
+    leaves_carcass: false,
+    picks_up: &[],
+    behaviors: &[],
+    swims: false,
+    flee_threshold: None,
+};
+
+const BERRY_SHRUB_STATS: ActorBaseStats = ActorBaseStats {
+    max_hp: 6,
+    attack_power: 0,
+    defense_power: 1,
+    sight_range: 0,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 0,
+    leaves_carcass: false,
+    picks_up: &[],
+    behaviors: &[],
+    swims: false,
+    flee_threshold: None,
+};
+
+const HERON_STATS: ActorBaseStats = ActorBaseStats {
+    max_hp: 4,
+    attack_power: 1,
+    defense_power: 0,
+    sight_range: 5,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 3,
+    leaves_carcass: true,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::MeleeAttack),
+        always(Behavior::Pace(vec2(0, 1))),
+    ],
+    swims: false,
+    flee_threshold: None,
+};
+
+const STARLIGHT_FAIRY_STATS: ActorBaseStats = ActorBaseStats {
+    max_hp: 5,
+    attack_power: 1,
+    defense_power: 0,
+    sight_range: 5,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 4,
+    leaves_carcass: false,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::MeleeAttack),
+        always(Behavior::InflictDazzle),
+        always(Behavior::Wander(0.667)),
+    ],
+    swims: false,
+    flee_threshold: None,
+};
+
+const MERMAID_STATS: ActorBaseStats = ActorBaseStats {
+    max_hp: 6,
+    attack_power: 2,
+    defense_power: 0,
+    sight_range: 4,
+    stealth: 0,
+    speed: 100,
+    attack_cost: 100,
+    experience: 5,
+    leaves_carcass: true,
+    picks_up: &[],
+    behaviors: &[
+        always(Behavior::MeleeAttack),
+        BehaviorRule { behavior: Behavior::Approach, condition: BehaviorCondition::PlayerWithin(4) },
+        always(Behavior::Wander(0.25)),
+    ],
+    swims: true,
+    flee_threshold: None,
 };
 
 pub fn get_base_stats(actor_type: ActorType) -> &'static ActorBaseStats {
@@ -95,6 +448,134 @@ pub fn get_base_stats(actor_type: ActorType) -> &'static ActorBaseStats {
         ActorType::ToothyStarling => &TOOTHY_STARLING_STATS,
         ActorType::DustySkeleton => &DUSTY_SKELETON_STATS,
         ActorType::BlueJelly => &BLUE_JELLY_STATS,
+        ActorType::BloodflowerBush => &BLOODFLOWER_BUSH_STATS,
+        ActorType::BerryShrub => &BERRY_SHRUB_STATS,
+        ActorType::Heron => &HERON_STATS,
+        ActorType::StarlightFairy => &STARLIGHT_FAIRY_STATS,
+        ActorType::Mermaid => &MERMAID_STATS,
+    }
+}
+
+pub struct LevelData {
+    // Total experience needed to reach the level
+    pub experience_required: i32,
+    pub max_hp_bonus: i32,
+    pub attack_bonus: i32,
+    pub defense_bonus: i32,
+}
+
+// Levels after the first, in order. The player starts at level 1.
+const LEVELS: &[LevelData] = &[
+    LevelData { experience_required: 10, max_hp_bonus: 4, attack_bonus: 1, defense_bonus: 0 },
+    LevelData { experience_required: 25, max_hp_bonus: 4, attack_bonus: 0, defense_bonus: 1 },
+    LevelData { experience_required: 45, max_hp_bonus: 4, attack_bonus: 1, defense_bonus: 0 },
+    LevelData { experience_required: 70, max_hp_bonus: 4, attack_bonus: 0, defense_bonus: 1 },
+    LevelData { experience_required: 100, max_hp_bonus: 4, attack_bonus: 1, defense_bonus: 0 },
+    LevelData { experience_required: 140, max_hp_bonus: 4, attack_bonus: 0, defense_bonus: 1 },
+    LevelData { experience_required: 190, max_hp_bonus: 4, attack_bonus: 1, defense_bonus: 0 },
+    LevelData { experience_required: 250, max_hp_bonus: 4, attack_bonus: 1, defense_bonus: 1 },
+];
+
+// Returns None at the maximum level
+pub fn get_next_level_data(current_level: i32) -> Option<&'static LevelData> {
+    LEVELS.get((current_level - 1) as usize)
+}
+
+// The player regains 1 HP every this many turns, less a couple of turns per level
+pub const BASE_REGENERATION_INTERVAL: i32 = 20;
+pub const MIN_REGENERATION_INTERVAL: i32 = 6;
+
+// Resting stops after this many turns even if nothing happened
+pub const MAX_REST_TURNS: u32 = 500;
+
+// The hunger clock counts satiety down by 1 every turn. It's optional, see GameInstance::hunger_enabled.
+pub const MAX_SATIETY: i32 = 2000;
+pub const HUNGRY_SATIETY: i32 = 400;
+// Starving players lose 1 HP every this many turns
+pub const STARVATION_DAMAGE_INTERVAL: u32 = 5;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HungerState {
+    Fed,
+    Hungry,
+    Starving,
+}
+
+pub fn get_hunger_state(satiety: i32) -> HungerState {
+    if satiety <= 0 {
+        HungerState::Starving
+    } else if satiety <= HUNGRY_SATIETY {
+        HungerState::Hungry
+    } else {
+        HungerState::Fed
+    }
+}
+
+// Energy an actor needs to take an action. Actors gain their speed in energy every turn, so an actor with a
+// speed of ACTION_COST acts once per turn.
+pub const ACTION_COST: i32 = 100;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SpeedCondition {
+    Slowed,
+}
+
+pub const CHILLED_TURNS: i32 = 5;
+
+// Dazzled actors sometimes miss their attacks
+pub const DAZZLE_TURNS: i32 = 4;
+pub const DAZZLE_COOLDOWN: i32 = 3;
+pub const DAZZLE_MISS_CHANCE: f32 = 0.25;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Awareness {
+    Asleep,
+    Wandering,
+    Hunting,
+    // Heading for where the player was last seen or heard
+    Searching,
+    // Running from the player until healed enough to rally
+    Fleeing,
+}
+
+pub const MONSTER_ASLEEP_CHANCE: f32 = 0.35;
+// Chance each turn that a sleeping monster wakes while the player is in sight but not adjacent
+pub const MONSTER_WAKE_CHANCE: f32 = 0.1;
+pub const COMBAT_NOISE_RADIUS: i32 = 6;
+
+// Fleeing monsters regain 1 HP every this many actions, and rally once back to this fraction of their max HP
+pub const FLEEING_REGENERATION_INTERVAL: i32 = 3;
+pub const RALLY_HP_FRACTION: f32 = 0.75;
+
+pub struct HarvestData {
+    pub item_type: ItemType,
+    // Yield when harvested by hand, e.g. by fighting the plant
+    pub basic_yield: i32,
+    // Yield when harvested with a tool like the moonlight knife
+    pub tool_yield: i32,
+    pub regrow_turns: i32,
+}
+
+const BLOODFLOWER_BUSH_HARVEST: HarvestData = HarvestData {
+    item_type: ItemType::Bloodflower,
+    basic_yield: 1,
+    tool_yield: 2,
+    regrow_turns: 60,
+};
+
+const BERRY_SHRUB_HARVEST: HarvestData = HarvestData {
+    item_type: ItemType::Darkberry,
+    basic_yield: 3,
+    tool_yield: 5,
+    regrow_turns: 40,
+};
+
+// Returns None for actors that can't be harvested. Harvestable actors are passive and never attack.
+pub fn get_harvest_data(actor_type: ActorType) -> Option<&'static HarvestData> {
+    match actor_type {
+        ActorType::BloodflowerBush => Some(&BLOODFLOWER_BUSH_HARVEST),
+        ActorType::BerryShrub => Some(&BERRY_SHRUB_HARVEST),
+        _ => None,
     }
 }
 
@@ -103,6 +584,7 @@ pub fn get_base_stats(actor_type: ActorType) -> &'static ActorBaseStats {
 pub enum ItemType {
     LumpOfBlackstone,
     //LumpOfWhitestone,
+    Javelin,
     BlackstoneSpear,
     //WhitestoneSpear,
     CarmineSword,
@@ -116,6 +598,9 @@ pub enum ItemType {
     Bloodflower,
     ElephantFrond,
     ArmadilloFlower,
+    Darkberry,
+    Acorn,
+    Carcass,
     //Azureberry,
     //Indigoberry,
     //PoulticeOfPurple,
@@ -126,6 +611,41 @@ pub enum ItemType {
     WandOfIce,
 }
 
+pub const ALL_ITEM_TYPES: &[ItemType] = &[
+    ItemType::LumpOfBlackstone,
+    ItemType::Javelin,
+    ItemType::BlackstoneSpear,
+    ItemType::CarmineSword,
+    ItemType::BoneLamellar,
+    ItemType::CarmineChainmail,
+    ItemType::CarmineHelm,
+    ItemType::FeatheredCavalier,
+    ItemType::Bloodflower,
+    ItemType::ElephantFrond,
+    ItemType::ArmadilloFlower,
+    ItemType::Darkberry,
+    ItemType::Acorn,
+    ItemType::Carcass,
+    ItemType::MoonlightKnife,
+    ItemType::WandOfIce,
+];
+
+// For grouping the inventory. Weapons and armor can be equipped, consumables can be eaten.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
+pub enum ItemCategory {
+    Weapon,
+    Armor,
+    Consumable,
+    Wand,
+}
+
+pub const ALL_ITEM_CATEGORIES: &[ItemCategory] = &[
+    ItemCategory::Weapon,
+    ItemCategory::Armor,
+    ItemCategory::Consumable,
+    ItemCategory::Wand,
+];
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum EquipSlot {
     Weapon,
@@ -133,113 +653,223 @@ pub enum EquipSlot {
     Torso,
 }
 
+pub const ALL_EQUIP_SLOTS: &[EquipSlot] = &[
+    EquipSlot::Weapon,
+    EquipSlot::Headgear,
+    EquipSlot::Torso,
+];
+
 pub struct ItemData {
+    pub category: ItemCategory,
     pub equip_slot: EquipSlot,
     pub attack_bonus: Option<i32>,
     pub defense_bonus: Option<i32>,
     pub max_hp_bonus: Option<i32>,
     pub initial_wand_charges: Option<i32>,
+    // Energy spent attacking with the item wielded, if different from the wielder's unarmed attack
+    pub attack_cost: Option<i32>,
+    // Satiety restored by eating the item, see MAX_SATIETY
+    pub nutrition: Option<i32>,
+    // Several of these share one Item with a quantity
+    pub stackable: bool,
 }
 
+const JAVELIN_DATA: ItemData = ItemData {
+    category: ItemCategory::Weapon,
+    equip_slot: EquipSlot::Weapon,
+    attack_bonus: None,
+    defense_bonus: None,
+    max_hp_bonus: None,
+    initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: true,
+};
+
 const LUMP_OF_BLACKSTONE_DATA: ItemData = ItemData {
+    category: ItemCategory::Weapon,
     equip_slot: EquipSlot::Weapon,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: true,
 };
 
 const BLACKSTONE_SPEAR_DATA: ItemData = ItemData {
+    category: ItemCategory::Weapon,
     equip_slot: EquipSlot::Weapon,
     attack_bonus: Some(3),
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: Some(125),
+    nutrition: None,
+    stackable: false,
 };
 
 const CARMINE_CHAINMAIL_DATA: ItemData = ItemData {
+    category: ItemCategory::Armor,
     equip_slot: EquipSlot::Torso,
     attack_bonus: None,
     defense_bonus: Some(2),
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: false,
 };
 
 const BLOODFLOWER_DATA: ItemData = ItemData {
+    category: ItemCategory::Consumable,
     equip_slot: EquipSlot::Headgear,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: Some(200),
+    stackable: true,
 };
 
 const WAND_OF_ICE_DATA: ItemData = ItemData {
+    category: ItemCategory::Wand,
     equip_slot: EquipSlot::Weapon,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: Some(4),
+    attack_cost: None,
+    nutrition: None,
+    stackable: false,
 };
 
 const CARMINE_SWORD_DATA: ItemData = ItemData {
+    category: ItemCategory::Weapon,
     equip_slot: EquipSlot::Weapon,
     attack_bonus: Some(5),
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: Some(150),
+    nutrition: None,
+    stackable: false,
 };
 
 const MOONLIGHT_KNIFE_DATA: ItemData = ItemData {
+    category: ItemCategory::Weapon,
     equip_slot: EquipSlot::Weapon,
     attack_bonus: Some(1),
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: false,
 };
 
 const BONE_LAMELLAR_DATA: ItemData = ItemData {
+    category: ItemCategory::Armor,
     equip_slot: EquipSlot::Torso,
     attack_bonus: None,
     defense_bonus: Some(1),
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: false,
 };
 
 const FEATHERED_CAVALIER_DATA: ItemData = ItemData {
+    category: ItemCategory::Armor,
     equip_slot: EquipSlot::Headgear,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: Some(4),
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: false,
 };
 
 const CARMINE_HELM_DATA: ItemData = ItemData {
+    category: ItemCategory::Armor,
     equip_slot: EquipSlot::Headgear,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: Some(8),
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: None,
+    stackable: false,
 };
 
 const ELEPHANT_FROND_DATA: ItemData = ItemData {
+    category: ItemCategory::Consumable,
     equip_slot: EquipSlot::Headgear,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: Some(200),
+    stackable: true,
 };
 
 const ARMADILLO_FLOWER_DATA: ItemData = ItemData {
+    category: ItemCategory::Consumable,
+    equip_slot: EquipSlot::Headgear,
+    attack_bonus: None,
+    defense_bonus: None,
+    max_hp_bonus: None,
+    initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: Some(200),
+    stackable: true,
+};
+
+const ACORN_DATA: ItemData = ItemData {
+    category: ItemCategory::Consumable,
+    equip_slot: EquipSlot::Headgear,
+    attack_bonus: None,
+    defense_bonus: None,
+    max_hp_bonus: None,
+    initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: Some(400),
+    stackable: true,
+};
+
+const CARCASS_DATA: ItemData = ItemData {
+    category: ItemCategory::Consumable,
     equip_slot: EquipSlot::Headgear,
     attack_bonus: None,
     defense_bonus: None,
     max_hp_bonus: None,
     initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: Some(600),
+    stackable: false,
+};
+
+const DARKBERRY_DATA: ItemData = ItemData {
+    category: ItemCategory::Consumable,
+    equip_slot: EquipSlot::Headgear,
+    attack_bonus: None,
+    defense_bonus: None,
+    max_hp_bonus: None,
+    initial_wand_charges: None,
+    attack_cost: None,
+    nutrition: Some(100),
+    stackable: true,
 };
 
 pub fn get_item_data(item_type: ItemType) -> &'static ItemData {
     match item_type {
         ItemType::LumpOfBlackstone => &LUMP_OF_BLACKSTONE_DATA,
+        ItemType::Javelin => &JAVELIN_DATA,
         ItemType::BlackstoneSpear => &BLACKSTONE_SPEAR_DATA,
         ItemType::CarmineSword => &CARMINE_SWORD_DATA,
         ItemType::MoonlightKnife => &MOONLIGHT_KNIFE_DATA,
@@ -250,12 +880,100 @@ pub fn get_item_data(item_type: ItemType) -> &'static ItemData {
         ItemType::Bloodflower => &BLOODFLOWER_DATA,
         ItemType::ElephantFrond => &ELEPHANT_FROND_DATA,
         ItemType::ArmadilloFlower => &ARMADILLO_FLOWER_DATA,
+        ItemType::Darkberry => &DARKBERRY_DATA,
+        ItemType::Acorn => &ACORN_DATA,
+        ItemType::Carcass => &CARCASS_DATA,
         ItemType::WandOfIce => &WAND_OF_ICE_DATA,
     }
 }
 
+pub const JAVELIN_DAMAGE: i32 = 3;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProjectileLanding {
+    // The item comes to rest on the last tile reached
+    Drops,
+    // The item is used up
+    Shatters,
+    // The projectile isn't the item itself, e.g. a beam from a wand
+    Dissipates,
+}
+
+pub struct ProjectileData {
+    // In tiles
+    pub range: i32,
+    // Keeps flying after hitting an actor
+    pub piercing: bool,
+    // Actors within this many tiles of the impact point are hit as well, 0 to hit a single actor
+    pub blast_radius: i32,
+    pub landing: ProjectileLanding,
+}
+
+const LUMP_OF_BLACKSTONE_PROJECTILE: ProjectileData = ProjectileData {
+    range: 6,
+    piercing: false,
+    blast_radius: 0,
+    landing: ProjectileLanding::Shatters,
+};
+
+const JAVELIN_PROJECTILE: ProjectileData = ProjectileData {
+    range: 8,
+    piercing: false,
+    blast_radius: 0,
+    landing: ProjectileLanding::Drops,
+};
+
+const WAND_OF_ICE_PROJECTILE: ProjectileData = ProjectileData {
+    range: 12,
+    piercing: false,
+    blast_radius: 0,
+    landing: ProjectileLanding::Dissipates,
+};
+
+// Returns None for items that can't be thrown or fired
+pub fn get_projectile_data(item_type: ItemType) -> Option<&'static ProjectileData> {
+    match item_type {
+        ItemType::LumpOfBlackstone => Some(&LUMP_OF_BLACKSTONE_PROJECTILE),
+        ItemType::Javelin => Some(&JAVELIN_PROJECTILE),
+        ItemType::WandOfIce => Some(&WAND_OF_ICE_PROJECTILE),
+        _ => None,
+    }
+}
+
+pub struct LootEntry {
+    pub item_type: ItemType,
+    // Rolled separately for each entry
+    pub chance: f32,
+}
+
+const MOUSE_WARRIOR_LOOT: &[LootEntry] = &[
+    LootEntry { item_type: ItemType::BlackstoneSpear, chance: 0.4 },
+    LootEntry { item_type: ItemType::Bloodflower, chance: 0.2 },
+];
+
+const MOUSE_SKIRMISHER_LOOT: &[LootEntry] = &[
+    LootEntry { item_type: ItemType::Javelin, chance: 1.0 },
+    LootEntry { item_type: ItemType::LumpOfBlackstone, chance: 0.3 },
+];
+
+const DUSTY_SKELETON_LOOT: &[LootEntry] = &[
+    LootEntry { item_type: ItemType::BoneLamellar, chance: 0.15 },
+];
+
+// What monsters are carrying when they're generated
+pub fn get_loot_table(actor_type: ActorType) -> &'static [LootEntry] {
+    match actor_type {
+        ActorType::MouseWarrior => MOUSE_WARRIOR_LOOT,
+        ActorType::MouseSkirmisher => MOUSE_SKIRMISHER_LOOT,
+        ActorType::DustySkeleton => DUSTY_SKELETON_LOOT,
+        _ => &[],
+    }
+}
+
 pub const STEEL_THISTLE_CYCLE_MAX: i32 = 4;
 
+pub const ICE_MELT_TURNS: i32 = 12;
+
 #[allow(unused)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum MiscEntityType {
@@ -264,6 +982,12 @@ pub enum MiscEntityType {
     HealingFont,
 }
 
+pub const ALL_MISC_ENTITY_TYPES: &[MiscEntityType] = &[
+    MiscEntityType::SteelThistle,
+    MiscEntityType::TreasureChest,
+    MiscEntityType::HealingFont,
+];
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum GameEvent {
     Bonk { actor_id: u32 },
@@ -277,6 +1001,9 @@ pub enum GameEvent {
     ItemNotEdible { item_id: u32 },
     EffectHealed { actor_id: u32 },
     SlowedByWater { actor_id: u32 },
+    SlidOnIce { actor_id: u32 },
+    WaterFroze,
+    IceMelted,
     ActivatedItem { item_id: u32 },
     EffectIceDamage { actor_id: u32, damage: i32 },
     NoEffect { item_id: u32 },
@@ -288,5 +1015,32 @@ pub enum GameEvent {
     EffectStronger { actor_id: u32 },
     EffectTougher { actor_id: u32 },
     UsedHealingFont { actor_id: u32 },
+    Harvested { actor_id: u32, item_id: u32 },
+    NothingToHarvest { actor_id: u32 },
+    LevelUp { actor_id: u32, level: i32 },
+    Rested { turns: u32 },
+    MonsterFled { actor_id: u32 },
+    MonsterRallied { actor_id: u32 },
+    Dazzled { attacker_id: u32, defender_id: u32 },
+    DazzleMiss { attacker_id: u32, defender_id: u32 },
+    DazzleFaded { actor_id: u32 },
+    DroppedLoot { actor_id: u32, item_id: u32 },
+    PickedUpItem { actor_id: u32, item_id: u32 },
+    HungerChanged { actor_id: u32, hunger: HungerState },
+    StarvationDamage { actor_id: u32, damage: i32 },
+    CantRestNearMonsters,
+    EffectSlowed { actor_id: u32 },
+    SpeedReturnedToNormal { actor_id: u32 },
+    NoticedPlayer { actor_id: u32 },
+    WokeUp { actor_id: u32 },
     Winner,
 }
+
+// Things frontends can animate. Unlike events these aren't logged, they only cover the last command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VisualEffect {
+    // Thrown items and beams, in the order the tiles were crossed
+    Projectile { item_type: ItemType, path: Vec<TilePoint> },
+    Hit { position: TilePoint },
+    ThistleStrike { position: TilePoint },
+}
@@ -1,19 +1,23 @@
-use std::collections:: {HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections:: {BinaryHeap, HashMap, HashSet, VecDeque};
 
 use cgmath::vec2;
 use rand::Rng;
+use rand::prelude::IndexedRandom;
 
-use crate::data::{ActorType, CellType, ItemType, GameEvent, STEEL_THISTLE_CYCLE_MAX, TilePoint, TileDelta, TileSize, get_base_stats, get_item_data, ItemData, MiscEntityType};
+use crate::data::{ACTION_COST, ActorType, Awareness, BASE_REGENERATION_INTERVAL, Behavior, BehaviorCondition, BehaviorRule, CellType, CHILLED_TURNS, COMBAT_NOISE_RADIUS, DAZZLE_COOLDOWN, DAZZLE_MISS_CHANCE, DAZZLE_TURNS, FLEEING_REGENERATION_INTERVAL, ItemType, GameEvent, HungerState, ICE_MELT_TURNS, ItemCategory, JAVELIN_DAMAGE, MAX_REST_TURNS, MAX_SATIETY, MIN_REGENERATION_INTERVAL, MONSTER_ASLEEP_CHANCE, MONSTER_WAKE_CHANCE, NEIGHBORS, RALLY_HP_FRACTION, SpeedCondition, STARVATION_DAMAGE_INTERVAL, STEEL_THISTLE_CYCLE_MAX, TilePoint, TileDelta, TileSize, get_base_stats, get_harvest_data, get_hunger_state, get_item_data, get_loot_table, get_next_level_data, get_projectile_data, get_terrain_data, ItemData, MiscEntityType, ProjectileData, ProjectileLanding, VisualEffect};
 use crate::{generate, generate::RoomGenerationConfig};
 
 #[repr(C)]
 #[derive(Clone, Debug, Default)]
 pub struct Cell {
     cell_type: CellType,
+    // Turns until temporary terrain (e.g. ice) reverts.
+    timer: i32,
 }
 
 fn distance(p1: TilePoint, p2: TilePoint) -> i32 {
-    return (p1.x - p2.x).abs() + (p1.y - p2.y).abs();
+    (p1.x - p2.x).abs() + (p1.y - p2.y).abs()
 }
 
 // Orthogonal line algorithm, very similar to Bresenham's but only moves in cardinal directions
@@ -71,18 +75,130 @@ fn roll_magical_damage(power: i32, deterministic: bool) -> i32 {
     }
 }
 
+// What a monster remembers between actions for its behaviors, see data::Behavior
+#[derive(Clone, Debug)]
+struct BehaviorState {
+    patrol_step: usize,
+    heading: TileDelta,
+    pace_reversed: bool,
+    dazzle_cooldown: i32,
+    // Actions spent fleeing, for regenerating along the way
+    flee_actions: i32,
+}
+
+impl Default for BehaviorState {
+    fn default() -> Self {
+        BehaviorState {
+            patrol_step: 0,
+            heading: vec2(1, 0),
+            pace_reversed: false,
+            dazzle_cooldown: 0,
+            flee_actions: 0,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Actor {
     pub id: u32,
     pub actor_type: ActorType,
     pub position: TilePoint,
-    ai_data: i32,
-    skip_next_turn: bool,
+    behavior_state: BehaviorState,
+    // Turns until a harvested plant can be harvested again
+    regrow_turns: i32,
+    // Actors act whenever this reaches ACTION_COST, see Room::run_actor_turns
+    energy: i32,
+    speed: i32,
+    attack_cost: i32,
+    pub speed_condition: Option<SpeedCondition>,
+    pub speed_condition_turns: i32,
+    pub dazzled_turns: i32,
+    pub awareness: Awareness,
+    // Where the player was last seen or heard, for hunting and searching monsters
+    search_target: TilePoint,
     pub is_dead: bool,
-    pub max_hp: i32,
     pub current_hp: i32,
-    pub attack_power: i32,
-    pub defense_power: i32,
+    // Max HP, attack and defense come from these added together, see max_hp() and friends
+    pub stats: StatSources,
+    sight_range: i32,
+    pub stealth: i32,
+    pub experience: i32,
+    pub level: i32,
+    // Only counts down for the player, and only with the hunger clock on
+    pub satiety: i32,
+    // Items carried by monsters. The player's are in Room::player_inventory.
+    pub inventory: Vec<u32>,
+}
+
+impl Actor {
+    // Plants don't notice anything, they just grow
+    pub fn has_awareness(&self) -> bool {
+        self.actor_type != ActorType::Player && get_harvest_data(self.actor_type).is_none()
+    }
+
+    pub fn effective_speed(&self) -> i32 {
+        match self.speed_condition {
+            Some(SpeedCondition::Slowed) => self.speed / 2,
+            None => self.speed,
+        }
+    }
+
+    fn set_speed_condition(&mut self, condition: SpeedCondition, turns: i32) {
+        self.speed_condition = Some(condition);
+        self.speed_condition_turns = turns;
+    }
+
+    pub fn is_harvested(&self) -> bool {
+        get_harvest_data(self.actor_type).is_some() && self.regrow_turns > 0
+    }
+
+    pub fn max_hp(&self) -> i32 {
+        self.stats.total().max_hp
+    }
+
+    pub fn attack_power(&self) -> i32 {
+        self.stats.total().attack
+    }
+
+    pub fn defense_power(&self) -> i32 {
+        self.stats.total().defense
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StatBonus {
+    pub max_hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+}
+
+impl StatBonus {
+    fn add(&mut self, other: StatBonus) {
+        self.max_hp += other.max_hp;
+        self.attack += other.attack;
+        self.defense += other.defense;
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StatSources {
+    // From ActorBaseStats
+    pub base: StatBonus,
+    pub level: StatBonus,
+    // Permanent effects, e.g. from eating an elephant frond
+    pub buffs: StatBonus,
+    // Equipped items, kept up to date by Room::update_gear_bonus
+    pub gear: StatBonus,
+}
+
+impl StatSources {
+    pub fn total(&self) -> StatBonus {
+        let mut total = self.base;
+        for source in [ self.level, self.buffs, self.gear ] {
+            total.add(source);
+        }
+        total
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -94,6 +210,10 @@ pub struct Item {
     pub equipped: bool,
     pub destroyed: bool,
     pub wand_charges: Option<i32>,
+    // Always 1 unless the item type is stackable
+    pub quantity: i32,
+    // Set while in the player's inventory. Kept for as long as the item is carried, so the player can learn them.
+    pub inventory_letter: Option<char>,
 }
 
 #[derive(Clone, Debug)]
@@ -105,7 +225,21 @@ pub struct MiscEntity {
     pub data: i32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RememberedActor {
+    pub actor_type: ActorType,
+    pub turn_seen: u32,
+}
+
+// What the player last saw on a tile, for drawing it once it's out of sight
+#[derive(Clone, Debug)]
+pub struct TileMemory {
+    pub actor: Option<RememberedActor>,
+    pub item_type: Option<ItemType>,
+    pub misc_entity: Option<MiscEntity>,
+}
+
+#[derive(Clone, Debug)]
 pub struct Room {
     pub size: TileSize,
     pub depth: i32,
@@ -116,10 +250,18 @@ pub struct Room {
     pub player_inventory: Vec<u32>,
     pub visible: HashSet<TilePoint>,
     pub explored: HashSet<TilePoint>,
+    // Only tiles that had something on them when last seen
+    pub memory: HashMap<TilePoint, TileMemory>,
     pub exits: HashMap<TilePoint, RoomGenerationConfig>,
     pub next_id: u32,
     pub player_index: usize,
     pub deterministic: bool,
+    // Cleared at the start of every command
+    pub visual_effects: Vec<VisualEffect>,
+    // The room as it was just before the first visual effect of the command, for frontends to animate over
+    pub before_visual_effects: Option<Box<Room>>,
+    // Copying the whole room isn't cheap, so it's skipped when nothing is going to be animated
+    pub keep_state_before_visual_effects: bool,
 }
 
 struct WalkResult {
@@ -143,22 +285,37 @@ impl Room {
             player_inventory: vec![],
             visible: HashSet::new(),
             explored: HashSet::new(),
+            memory: HashMap::new(),
             exits: HashMap::new(),
             next_id: 0,
             player_index: 0,
             deterministic: false,
+            visual_effects: vec![],
+            before_visual_effects: None,
+            keep_state_before_visual_effects: false,
         }
     }
 
     fn generate(player_start: Option<TilePoint>, config: RoomGenerationConfig) -> Self {
         let mut room = Self::new(config.size, config.depth);
         let gen_result = generate::generate_room(player_start, config.clone());
+        let mut rng = rand::rng();
         for x in 0..config.size.x { for y in 0..config.size.y {
             let pos = vec2(x as i32, y as i32);
             let cell = &gen_result.cells[x][y];
             room.set_cell(pos, cell.cell_type);
             if let Some(monster_type) = cell.monster {
                 room.create_actor(monster_type, pos);
+                let actor = room.actors.last_mut().unwrap();
+                if actor.has_awareness() && rng.random::<f32>() < MONSTER_ASLEEP_CHANCE {
+                    actor.awareness = Awareness::Asleep;
+                }
+                let actor_index = room.actors.len() - 1;
+                for loot in get_loot_table(monster_type) {
+                    if rng.random::<f32>() < loot.chance {
+                        room.give_item(actor_index, loot.item_type);
+                    }
+                }
             }
             if let Some(item_type) = cell.item {
                 room.create_item(item_type, pos);
@@ -167,8 +324,12 @@ impl Room {
                 room.create_misc_entity(entity_type, pos);
             }
         }}
+        // Each exit leads to a room of its own one level further down
         for &exit in gen_result.exits.iter() {
-            room.exits.insert(exit, config.clone());
+            let depth = config.depth + 1;
+            let size = generate::roll_room_size(&mut rng, depth);
+            let strategy = generate::roll_strategy(&mut rng, depth);
+            room.exits.insert(exit, RoomGenerationConfig { depth, size, strategy });
         }
         if player_start.is_none() {
             room.create_player(gen_result.player_start);
@@ -180,6 +341,12 @@ impl Room {
         self.cells[position.x as usize][position.y as usize].cell_type = cell_type;
     }
 
+    fn freeze_cell(&mut self, position: TilePoint) {
+        let cell = &mut self.cells[position.x as usize][position.y as usize];
+        cell.cell_type = CellType::Ice;
+        cell.timer = ICE_MELT_TURNS;
+    }
+
     pub fn create_actor(&mut self, actor_type: ActorType, position: TilePoint) -> u32 {
         let id = self.next_id;
         let stats = get_base_stats(actor_type);
@@ -187,13 +354,28 @@ impl Room {
             id,
             actor_type,
             position,
-            ai_data: 0,
-            skip_next_turn: false,
+            behavior_state: BehaviorState::default(),
+            regrow_turns: 0,
+            energy: ACTION_COST,
+            speed: stats.speed,
+            attack_cost: stats.attack_cost,
+            speed_condition: None,
+            speed_condition_turns: 0,
+            dazzled_turns: 0,
+            awareness: Awareness::Wandering,
+            search_target: position,
             is_dead: false,
-            max_hp: stats.max_hp,
             current_hp: stats.max_hp,
-            attack_power: stats.attack_power,
-            defense_power: stats.defense_power,
+            stats: StatSources {
+                base: StatBonus { max_hp: stats.max_hp, attack: stats.attack_power, defense: stats.defense_power },
+                ..StatSources::default()
+            },
+            sight_range: stats.sight_range,
+            stealth: stats.stealth,
+            experience: 0,
+            level: 1,
+            satiety: MAX_SATIETY,
+            inventory: vec![],
         });
         self.next_id += 1;
         id
@@ -227,6 +409,8 @@ impl Room {
             equipped: false,
             destroyed: false,
             wand_charges: get_item_data(item_type).initial_wand_charges,
+            quantity: 1,
+            inventory_letter: None,
         });
         self.next_id += 1;
         id
@@ -268,6 +452,13 @@ impl Room {
         result
     }
 
+    pub fn give_item(&mut self, actor_index: usize, item_type: ItemType) -> u32 {
+        let item_id = self.create_item(item_type, self.actors[actor_index].position);
+        self.get_item_mut(item_id).carried = true;
+        self.actors[actor_index].inventory.push(item_id);
+        item_id
+    }
+
     pub fn destroy_item(&mut self, item_id: u32) {
         self.get_item_mut(item_id).destroyed = true;
         self.player_inventory.swap_remove(self.player_inventory.iter().position(|&id| id == item_id).unwrap());
@@ -333,36 +524,325 @@ impl Room {
     fn modify_hp(&mut self, actor_index: usize, delta: i32) {
         let actor = &mut self.actors[actor_index];
         actor.current_hp += delta;
-        if actor.current_hp > actor.max_hp {
-            actor.current_hp = actor.max_hp;
+        if actor.current_hp > actor.max_hp() {
+            actor.current_hp = actor.max_hp();
         } else if actor.current_hp <= 0 {
             actor.is_dead = true;
         }
     }
 
+    // Call once an actor has been killed, to drop what they were carrying
+    fn handle_death(&mut self, actor_index: usize) -> Vec<GameEvent> {
+        let actor_id = self.actors[actor_index].id;
+        let position = self.actors[actor_index].position;
+        let mut events = vec![ GameEvent::Death { actor_id } ];
+        for item_id in std::mem::take(&mut self.actors[actor_index].inventory) {
+            let item = self.get_item_mut(item_id);
+            item.carried = false;
+            item.position = position;
+            events.push(GameEvent::DroppedLoot { actor_id, item_id });
+            self.merge_into_floor_stack(item_id);
+        }
+        events
+    }
+
+    // Carcasses are only food, so they're left only when the hunger clock is on. Goes through the events rather
+    // than handle_death so that the hunger option stays with GameInstance.
+    fn leave_carcasses(&mut self, events: &[GameEvent]) {
+        for event in events {
+            let GameEvent::Death { actor_id } = *event else {
+                continue;
+            };
+            let Some(actor) = self.actors.iter().find(|actor| actor.id == actor_id) else {
+                continue;
+            };
+            if get_base_stats(actor.actor_type).leaves_carcass {
+                let position = actor.position;
+                self.create_item(ItemType::Carcass, position);
+            }
+        }
+    }
+
+    fn get_attack_cost(&self, actor_index: usize) -> i32 {
+        if actor_index == self.player_index {
+            let weapon_cost = self.player_inventory.iter()
+                .map(|&item_id| self.get_item(item_id))
+                .filter(|item| item.equipped)
+                .find_map(|item| get_item_data(item.item_type).attack_cost);
+            if let Some(cost) = weapon_cost {
+                return cost;
+            }
+        }
+        self.actors[actor_index].attack_cost
+    }
+
+    // Call before changing anything that a visual effect is about to show
+    fn save_state_before_visual_effects(&mut self) {
+        if self.keep_state_before_visual_effects && self.before_visual_effects.is_none() {
+            self.before_visual_effects = Some(Box::new(self.clone()));
+        }
+    }
+
     fn melee_attack(&mut self, attacker_index: usize, defender_index: usize) -> Vec<GameEvent> {
-        let attack_power = self.actors[attacker_index].attack_power;
+        // Attacks count as one action already, anything beyond that is extra
+        self.actors[attacker_index].energy -= self.get_attack_cost(attacker_index) - ACTION_COST;
+        if self.actors[attacker_index].dazzled_turns > 0 && !self.deterministic && rand::rng().random::<f32>() < DAZZLE_MISS_CHANCE {
+            return vec![ GameEvent::DazzleMiss { attacker_id: self.actors[attacker_index].id, defender_id: self.actors[defender_index].id } ];
+        }
+        let attack_power = self.actors[attacker_index].attack_power();
         let incoming_damage = roll_physical_damage(attack_power, self.deterministic);
-        let damage = (incoming_damage - self.actors[defender_index].defense_power).max(0);
+        let damage = (incoming_damage - self.actors[defender_index].defense_power()).max(0);
+        self.save_state_before_visual_effects();
         self.modify_hp(defender_index, -damage);
+        self.visual_effects.push(VisualEffect::Hit { position: self.actors[defender_index].position });
         let mut new_events = vec![
             GameEvent::MeleeAttack {
                 attacker_id: self.actors[attacker_index].id,
                 defender_id: self.actors[defender_index].id,
-                damage: damage,
+                damage,
             }
         ];
         if self.actors[defender_index].is_dead {
-            new_events.push(GameEvent::Death { actor_id: self.actors[defender_index].id });
+            new_events.append(&mut self.handle_death(defender_index));
+            if attacker_index == self.player_index {
+                new_events.append(&mut self.reward_kill(defender_index));
+            }
+        }
+        if attacker_index == self.player_index {
+            self.alert_to_player(defender_index);
         }
+        new_events.append(&mut self.make_noise(self.actors[defender_index].position, COMBAT_NOISE_RADIUS));
         new_events
     }
 
-    fn ai_walk_towards_player(&mut self, monster_index: usize) {
-        let actor_pos = self.actors[monster_index].position;
+    fn reward_kill(&mut self, victim_index: usize) -> Vec<GameEvent> {
+        let mut events = vec![];
+        let experience = get_base_stats(self.actors[victim_index].actor_type).experience;
+        let player = self.get_player_mut();
+        player.experience += experience;
+        while let Some(level_data) = get_next_level_data(player.level) {
+            if player.experience < level_data.experience_required {
+                break;
+            }
+            player.level += 1;
+            player.stats.level.add(StatBonus {
+                max_hp: level_data.max_hp_bonus,
+                attack: level_data.attack_bonus,
+                defense: level_data.defense_bonus,
+            });
+            player.current_hp += level_data.max_hp_bonus;
+            events.push(GameEvent::LevelUp { actor_id: player.id, level: player.level });
+        }
+        events
+    }
+
+    // Whoever gets hurt by the player knows exactly where they are
+    fn alert_to_player(&mut self, monster_index: usize) {
+        let player_pos = self.get_player().position;
+        let actor = &mut self.actors[monster_index];
+        if actor.has_awareness() && !actor.is_dead {
+            if actor.awareness != Awareness::Fleeing {
+                actor.awareness = Awareness::Hunting;
+            }
+            actor.search_target = player_pos;
+        }
+    }
+
+    // Wakes and draws monsters within the radius towards the source of the noise
+    fn make_noise(&mut self, position: TilePoint, radius: i32) -> Vec<GameEvent> {
+        let mut events = vec![];
+        for i in 0..self.actors.len() {
+            let actor = &mut self.actors[i];
+            if !actor.has_awareness() || actor.is_dead || distance(actor.position, position) > radius {
+                continue;
+            }
+            match actor.awareness {
+                Awareness::Hunting | Awareness::Fleeing => (),
+                Awareness::Asleep => {
+                    actor.awareness = Awareness::Searching;
+                    actor.search_target = position;
+                    if self.visible.contains(&actor.position) {
+                        events.push(GameEvent::WokeUp { actor_id: actor.id });
+                    }
+                },
+                Awareness::Wandering | Awareness::Searching => {
+                    actor.awareness = Awareness::Searching;
+                    actor.search_target = position;
+                },
+            }
+        }
+        events
+    }
+
+    fn harvest(&mut self, harvester_index: usize, plant_index: usize, with_tool: bool) -> Vec<GameEvent> {
+        let plant_id = self.actors[plant_index].id;
+        let harvest_data = get_harvest_data(self.actors[plant_index].actor_type).expect("harvest called on actor that can't be harvested");
+        if self.actors[plant_index].is_harvested() {
+            return vec![ GameEvent::NothingToHarvest { actor_id: plant_id } ];
+        }
+        let mut events = vec![];
+        let position = self.actors[harvester_index].position;
+        let yield_count = if with_tool { harvest_data.tool_yield } else { harvest_data.basic_yield };
+        if get_item_data(harvest_data.item_type).stackable {
+            let item_id = self.create_item(harvest_data.item_type, position);
+            self.get_item_mut(item_id).quantity = yield_count;
+            events.push(GameEvent::Harvested { actor_id: plant_id, item_id });
+            self.merge_into_floor_stack(item_id);
+        } else {
+            for _ in 0..yield_count {
+                let item_id = self.create_item(harvest_data.item_type, position);
+                events.push(GameEvent::Harvested { actor_id: plant_id, item_id });
+            }
+        }
+        self.actors[plant_index].regrow_turns = harvest_data.regrow_turns;
+        events
+    }
+
+    fn find_carried_item(&self, actor_index: usize, item_type: ItemType) -> Option<u32> {
+        self.actors[actor_index].inventory.iter().copied().find(|&item_id| self.get_item(item_id).item_type == item_type)
+    }
+
+    fn can_perceive_player(&self, monster_index: usize) -> bool {
+        let player = self.get_player();
+        let monster = &self.actors[monster_index];
+        // Sight lines work both ways, so the player's field of view doubles as every monster's
+        if !self.visible.contains(&monster.position) {
+            return false;
+        }
+        let player_distance = distance(player.position, monster.position);
+        if player_distance > (monster.sight_range - player.stealth).max(1) {
+            return false;
+        }
+        match get_terrain_data(self.get_cell_type(player.position)).concealment_range {
+            Some(range) => player_distance <= range,
+            None => true,
+        }
+    }
+
+    fn update_awareness(&mut self, monster_index: usize) -> Vec<GameEvent> {
+        let mut events = vec![];
+        let perceives_player = self.can_perceive_player(monster_index);
+        let player_pos = self.get_player().position;
+        let deterministic = self.deterministic;
+        let actor = &mut self.actors[monster_index];
+        match actor.awareness {
+            Awareness::Asleep => {
+                if !perceives_player {
+                    return events;
+                }
+                // Sleepers always stir when someone is right next to them
+                let wakes = distance(player_pos, actor.position) <= 1
+                    || (!deterministic && rand::rng().random::<f32>() < MONSTER_WAKE_CHANCE);
+                if wakes {
+                    actor.awareness = Awareness::Hunting;
+                    actor.search_target = player_pos;
+                    events.push(GameEvent::WokeUp { actor_id: actor.id });
+                }
+            },
+            Awareness::Wandering | Awareness::Searching => {
+                if perceives_player {
+                    actor.awareness = Awareness::Hunting;
+                    actor.search_target = player_pos;
+                    events.push(GameEvent::NoticedPlayer { actor_id: actor.id });
+                } else if actor.awareness == Awareness::Searching && actor.position == actor.search_target {
+                    actor.awareness = Awareness::Wandering;
+                }
+            },
+            Awareness::Hunting => {
+                if perceives_player {
+                    actor.search_target = player_pos;
+                } else {
+                    actor.awareness = Awareness::Searching;
+                }
+            },
+            Awareness::Fleeing => {
+                if perceives_player {
+                    actor.search_target = player_pos;
+                }
+            },
+        }
+        events
+    }
+
+    // Breaks monsters that are too hurt to keep fighting, and rallies them once they've recovered
+    fn update_morale(&mut self, monster_index: usize) -> Vec<GameEvent> {
+        let Some(flee_threshold) = get_base_stats(self.actors[monster_index].actor_type).flee_threshold else {
+            return vec![];
+        };
+        let player_pos = self.get_player().position;
+        let actor = &mut self.actors[monster_index];
+        let max_hp = actor.max_hp() as f32;
+        if actor.awareness == Awareness::Fleeing {
+            actor.behavior_state.flee_actions += 1;
+            if actor.behavior_state.flee_actions % FLEEING_REGENERATION_INTERVAL == 0 {
+                actor.current_hp = (actor.current_hp + 1).min(actor.max_hp());
+            }
+            if actor.current_hp as f32 >= (max_hp * RALLY_HP_FRACTION).ceil() {
+                // Comes back looking for the player
+                actor.awareness = Awareness::Searching;
+                actor.search_target = player_pos;
+                return vec![ GameEvent::MonsterRallied { actor_id: actor.id } ];
+            }
+        } else if actor.current_hp as f32 <= (max_hp * flee_threshold).ceil() {
+            actor.awareness = Awareness::Fleeing;
+            actor.behavior_state.flee_actions = 0;
+            return vec![ GameEvent::MonsterFled { actor_id: actor.id } ];
+        }
+        vec![]
+    }
+
+    fn is_walkable(&self, position: TilePoint) -> bool {
+        !matches!(self.get_cell_type(position), CellType::DefaultWall | CellType::OutOfBounds)
+    }
+
+    // Dijkstra map for running away, in tenths of a step. It starts as the distance from the player scaled by
+    // -1.2, then gets smoothed so that fleeing monsters head for open space rather than the nearest dead end,
+    // even if that means doubling back past the player. Lower is safer.
+    fn compute_flee_map(&self) -> Vec<Vec<i32>> {
         let player_pos = self.get_player().position;
-        let dx = player_pos.x - actor_pos.x;
-        let dy = player_pos.y - actor_pos.y;
+        let mut flee_map = vec![ vec![ i32::MAX ; self.size.y ] ; self.size.x ];
+        let mut pending = VecDeque::from([ (player_pos, 0) ]);
+        flee_map[player_pos.x as usize][player_pos.y as usize] = 0;
+        while let Some((current, steps)) = pending.pop_front() {
+            for &(dx, dy) in NEIGHBORS.iter() {
+                let adj = vec2(current.x + dx, current.y + dy);
+                if self.is_walkable(adj) && flee_map[adj.x as usize][adj.y as usize] == i32::MAX {
+                    flee_map[adj.x as usize][adj.y as usize] = -12 * (steps + 1);
+                    pending.push_back((adj, steps + 1));
+                }
+            }
+        }
+
+        let mut heap = BinaryHeap::new();
+        #[allow(clippy::needless_range_loop)]
+        for x in 0..self.size.x { for y in 0..self.size.y {
+            if flee_map[x][y] != i32::MAX {
+                heap.push(Reverse((flee_map[x][y], x as i32, y as i32)));
+            }
+        }}
+        while let Some(Reverse((value, x, y))) = heap.pop() {
+            if value > flee_map[x as usize][y as usize] {
+                continue;
+            }
+            for &(dx, dy) in NEIGHBORS.iter() {
+                let adj = vec2(x + dx, y + dy);
+                if !self.is_walkable(adj) {
+                    continue;
+                }
+                let adj_value = &mut flee_map[adj.x as usize][adj.y as usize];
+                if value + 10 < *adj_value {
+                    *adj_value = value + 10;
+                    heap.push(Reverse((value + 10, adj.x, adj.y)));
+                }
+            }
+        }
+        flee_map
+    }
+
+    fn ai_walk_towards(&mut self, monster_index: usize, target: TilePoint) {
+        let actor_pos = self.actors[monster_index].position;
+        let dx = target.x - actor_pos.x;
+        let dy = target.y - actor_pos.y;
         let walk_x = vec2(dx.signum(), 0);
         let can_walk_x = self.can_actor_walk(monster_index, walk_x);
         let walk_y = vec2(0, dy.signum());
@@ -379,6 +859,9 @@ impl Room {
         };
         if delta.x != 0 || delta.y != 0 {
             self.actor_walk(monster_index, delta);
+        } else if self.actors[monster_index].awareness == Awareness::Searching {
+            // Stuck, give up rather than waiting at the wall forever
+            self.actors[monster_index].awareness = Awareness::Wandering;
         }
     }
 
@@ -389,161 +872,280 @@ impl Room {
         if self.actors[index].is_dead {
             return vec![];
         }
-        if self.actors[index].skip_next_turn {
-            self.actors[index].skip_next_turn = false;
-            return vec![];
+        let mut new_events = vec![];
+        if self.actors[index].has_awareness() {
+            let was_asleep = self.actors[index].awareness == Awareness::Asleep;
+            new_events.append(&mut self.update_awareness(index));
+            // Sleeping or waking up takes the whole turn
+            if was_asleep {
+                self.actors[index].energy = self.actors[index].energy.min(0);
+                return new_events;
+            }
+            new_events.append(&mut self.update_morale(index));
         }
+        if self.actors[index].regrow_turns > 0 {
+            self.actors[index].regrow_turns -= 1;
+        }
+        new_events.append(&mut self.run_behavior_rules(index, get_base_stats(self.actors[index].actor_type).behaviors));
+        new_events
+    }
+
+    // Runs the first rule whose condition holds and whose behavior applies right now
+    fn run_behavior_rules(&mut self, index: usize, rules: &[BehaviorRule]) -> Vec<GameEvent> {
         let mut new_events = vec![];
-        match self.actors[index].actor_type {
-            ActorType::Player => unreachable!(),
-            ActorType::Toad | ActorType::ToothyStarling => {
-                let distance_to_player = distance(self.get_player().position, self.actors[index].position);
-                if  distance_to_player == 1 {
-                    new_events.append(&mut self.melee_attack(index, self.player_index));
+        let player_distance = distance(self.get_player().position, self.actors[index].position);
+        for rule in rules {
+            let condition_met = match rule.condition {
+                BehaviorCondition::Always => true,
+                BehaviorCondition::PlayerWithin(range) => player_distance <= range,
+            };
+            if !condition_met {
+                continue;
+            }
+            if let Some(mut events) = self.run_behavior(index, rule.behavior) {
+                new_events.append(&mut events);
+                break;
+            }
+        }
+        new_events
+    }
+
+    // Returns None if the behavior doesn't apply right now, so the next one can be tried
+    fn run_behavior(&mut self, index: usize, behavior: Behavior) -> Option<Vec<GameEvent>> {
+        let awareness = self.actors[index].awareness;
+        let actor_pos = self.actors[index].position;
+        let player_pos = self.get_player().position;
+        match behavior {
+            Behavior::Flee => {
+                if awareness != Awareness::Fleeing {
+                    return None;
+                }
+                let flee_map = self.compute_flee_map();
+                let value_at = |p: TilePoint| flee_map[p.x as usize][p.y as usize];
+                let best_step = NEIGHBORS.iter()
+                    .map(|&(dx, dy)| vec2(dx, dy))
+                    .filter(|&delta| self.can_actor_walk(index, delta))
+                    .filter(|&delta| value_at(actor_pos + delta) < value_at(actor_pos))
+                    .min_by_key(|&delta| value_at(actor_pos + delta));
+                if let Some(delta) = best_step {
+                    self.actor_walk(index, delta);
+                    Some(vec![])
+                } else if distance(player_pos, actor_pos) == 1 {
+                    // Cornered
+                    Some(self.melee_attack(index, self.player_index))
                 } else {
-                    const TOAD_PATROL_PATTERN: &[&[TileDelta]] = &[
-                        &[vec2(1, 0)],
-                        &[vec2(0, 1)],
-                        &[vec2(-1, 0)],
-                        &[vec2(0, -1)],
-                    ];
-                    // ..*..
-                    // *.*.*
-                    // .....
-                    // .*.*.
-                    const STARLING_PATROL_PATTERN: &[&[TileDelta]] = &[
-                        &[vec2(0, 1), vec2(1, 0), vec2(1, 0)],
-                        &[vec2(-1, 0), vec2(0, 1), vec2(0, 1)],
-                        &[vec2(0, -1), vec2(0, -1), vec2(-1, 0)],
-                        &[vec2(-1, 0), vec2(0, 1), vec2(0, 1)],
-                        &[vec2(0, -1), vec2(0, -1), vec2(-1, 0)],
-                        &[vec2(1, 0), vec2(1, 0), vec2(0, -1)],
-                    ];
-                    let patrol_pattern = match self.actors[index].actor_type {
-                        ActorType::Toad => TOAD_PATROL_PATTERN,
-                        ActorType::ToothyStarling => STARLING_PATROL_PATTERN,
-                        _ => unreachable!(),
-                    };
-                    let deltas = patrol_pattern[self.actors[index].ai_data as usize];
-                    for &delta in deltas.iter() {
-                        self.actor_walk(index, delta);
-                    }
-                    self.actors[index].ai_data += 1;
-                    if self.actors[index].ai_data >= patrol_pattern.len() as i32 {
-                        self.actors[index].ai_data = 0;
-                    }
+                    Some(vec![])
                 }
             },
-            ActorType::MouseWarrior | ActorType::BlueJelly => {
-                let should_act = match self.actors[index].actor_type {
-                    ActorType::BlueJelly => {
-                        let acts_this_turn = self.actors[index].ai_data % 2 == 0;
-                        self.actors[index].ai_data += 1;
-                        acts_this_turn
-                    },
-                    _ => true,
-                };
-                if should_act {
-                    let actor_pos = self.actors[index].position;
-                    let player_pos = self.get_player().position;
-                    let distance_to_player = distance(player_pos, actor_pos);
-                    if  distance_to_player == 1 {
-                        new_events.append(&mut self.melee_attack(index, self.player_index));
-                    } else {
-                        self.ai_walk_towards_player(index);
-                    }
+            Behavior::MeleeAttack => {
+                if awareness != Awareness::Hunting || distance(player_pos, actor_pos) != 1 {
+                    return None;
                 }
+                Some(self.melee_attack(index, self.player_index))
             },
-            ActorType::MouseSkirmisher => {
-                let actor_pos = self.actors[index].position;
-                let player_pos = self.get_player().position;
-                let distance_to_player = distance(player_pos, actor_pos);
-                if distance_to_player == 1 {
-                    new_events.append(&mut self.melee_attack(index, self.player_index));
-                } else if self.actors[index].ai_data == 0 && (actor_pos.x == player_pos.x || actor_pos.y == player_pos.y) {
-                    self.actors[index].ai_data = 1;
-                    let direction = if actor_pos.x == player_pos.x {
-                        vec2(0, (player_pos.y - actor_pos.y).signum())
-                    } else {
-                        vec2((player_pos.x - actor_pos.x).signum(), 0)
-                    };
-                    // TODO: This is duplicated with activate_item_by_direction
-                    let mut current_pos = actor_pos;
-                    loop {
-                        current_pos += direction;
-                        let hit_actors = self.find_actors_at(current_pos, false);
-                        if !hit_actors.is_empty() {
-                            let hit_index = hit_actors[0];
-                            let hit_actor_id = self.actors[hit_index].id;
-                            let damage = roll_physical_damage(3, self.deterministic);
-                            self.modify_hp(hit_index, -damage);
-                            new_events.push(GameEvent::JavelinDamage { actor_id: hit_actor_id, damage });
-                            if self.actors[hit_index].is_dead {
-                                new_events.push(GameEvent::Death { actor_id: hit_actor_id });
-                            }
-                            break;
-                        }
-                        let cell_type = self.get_cell_type(current_pos);
-                        match cell_type {
-                            CellType::DefaultWall | CellType::OutOfBounds => break,
-                            _ => {},
-                        }
-                    }
+            Behavior::ThrowJavelin => {
+                if awareness != Awareness::Hunting || (actor_pos.x != player_pos.x && actor_pos.y != player_pos.y) {
+                    return None;
+                }
+                let stack_id = self.find_carried_item(index, ItemType::Javelin)?;
+                let direction = if actor_pos.x == player_pos.x {
+                    vec2(0, (player_pos.y - actor_pos.y).signum())
                 } else {
-                    self.ai_walk_towards_player(index);
+                    vec2((player_pos.x - actor_pos.x).signum(), 0)
+                };
+                let projectile = get_projectile_data(ItemType::Javelin).unwrap();
+                if distance(player_pos, actor_pos) > projectile.range {
+                    return None;
                 }
+                let javelin_id = self.split_stack(index, stack_id, 1);
+                Some(self.fire_projectile(index, javelin_id, direction, projectile))
             },
-            ActorType::DustySkeleton => {
-                let distance_to_player = distance(self.get_player().position, self.actors[index].position);
-                if  distance_to_player == 1 {
-                    new_events.append(&mut self.melee_attack(index, self.player_index));
-                } else {
-                    let delta = match self.actors[index].ai_data {
-                        0 => vec2(1, 0),
-                        1 => vec2(0, 1),
-                        2 => vec2(-1, 0),
-                        3 => vec2(0, -1),
-                        _ => unreachable!(),
-                    };
-                    let walk_result = self.actor_walk(index, delta);
-                    if !walk_result.succeeded {
-                        // Hit a wall, turn 90 degrees clockwise
-                        self.actors[index].ai_data = (self.actors[index].ai_data + 1) % 4;
-                    }
+            Behavior::Pursue => {
+                if awareness != Awareness::Hunting && awareness != Awareness::Searching {
+                    return None;
+                }
+                self.ai_walk_towards(index, self.actors[index].search_target);
+                Some(vec![])
+            },
+            Behavior::Approach => {
+                if awareness != Awareness::Hunting {
+                    return None;
+                }
+                self.ai_walk_towards(index, player_pos);
+                Some(vec![])
+            },
+            Behavior::Patrol(pattern) => {
+                let state = &mut self.actors[index].behavior_state;
+                let delta = pattern[state.patrol_step % pattern.len()];
+                state.patrol_step = (state.patrol_step + 1) % pattern.len();
+                self.actor_walk(index, delta);
+                Some(vec![])
+            },
+            Behavior::TurnAtWalls => {
+                let heading = self.actors[index].behavior_state.heading;
+                let walk_result = self.actor_walk(index, heading);
+                if !walk_result.succeeded {
+                    // Hit a wall, turn 90 degrees clockwise
+                    self.actors[index].behavior_state.heading = vec2(-heading.y, heading.x);
+                }
+                Some(vec![])
+            },
+            Behavior::Pace(direction) => {
+                let state = &mut self.actors[index].behavior_state;
+                let delta = if state.pace_reversed { -direction } else { direction };
+                if !self.actor_walk(index, delta).succeeded {
+                    let state = &mut self.actors[index].behavior_state;
+                    state.pace_reversed = !state.pace_reversed;
+                }
+                Some(vec![])
+            },
+            Behavior::InflictDazzle => {
+                let state = &mut self.actors[index].behavior_state;
+                state.dazzle_cooldown = (state.dazzle_cooldown - 1).max(0);
+                let lined_up = actor_pos.x == player_pos.x || actor_pos.y == player_pos.y;
+                if awareness != Awareness::Hunting || distance(player_pos, actor_pos) != 2 || !lined_up || state.dazzle_cooldown > 0 {
+                    return None;
                 }
+                state.dazzle_cooldown = DAZZLE_COOLDOWN;
+                self.get_player_mut().dazzled_turns = DAZZLE_TURNS;
+                Some(vec![ GameEvent::Dazzled { attacker_id: self.actors[index].id, defender_id: self.get_player().id } ])
+            },
+            Behavior::Wander(chance) => {
+                if !self.deterministic && rand::rng().random::<f32>() < chance {
+                    let &(dx, dy) = NEIGHBORS.choose(&mut rand::rng()).unwrap();
+                    self.actor_walk(index, vec2(dx, dy));
+                }
+                Some(vec![])
             },
         }
-        new_events
     }
 
-    fn update_misc_entity(&mut self, index: usize) -> Vec<GameEvent> {
+    // Gives every actor their speed in energy for the turn, then lets monsters act in order of energy until
+    // none can afford another action. The player acts separately, see GameInstance::execute_command.
+    fn run_actor_turns(&mut self) -> Vec<GameEvent> {
         let mut events = vec![];
-        match self.misc_entities[index].entity_type {
-            MiscEntityType::SteelThistle => {
-                self.misc_entities[index].data += 1;
-                if self.misc_entities[index].data > STEEL_THISTLE_CYCLE_MAX {
-                    self.misc_entities[index].data = 0;
+        for i in 0..self.actors.len() {
+            let visible = self.visible.contains(&self.actors[i].position);
+            let actor = &mut self.actors[i];
+            if actor.is_dead {
+                continue;
+            }
+            let speed = actor.effective_speed();
+            // Idle actors can't bank energy beyond one turn's worth
+            actor.energy = (actor.energy + speed).min(speed.max(ACTION_COST));
+            if actor.dazzled_turns > 0 {
+                actor.dazzled_turns -= 1;
+                if actor.dazzled_turns == 0 && visible {
+                    events.push(GameEvent::DazzleFaded { actor_id: actor.id });
                 }
-                match self.misc_entities[index].data {
-                    0..STEEL_THISTLE_CYCLE_MAX => (),
-                    STEEL_THISTLE_CYCLE_MAX => {
-                        // Strike actors
-                        for actor_index in self.find_actors_at(self.misc_entities[index].position, false) {
-                            self.modify_hp(actor_index, -1);
-                            events.push(GameEvent::SteelThistleHit { actor_id: self.actors[actor_index].id, damage: 1 });
-                            if self.actors[actor_index].is_dead {
-                                events.push(GameEvent::Death { actor_id: self.actors[actor_index].id });
-                            }
-                        }
-                    },
-                    _ => unreachable!(),
+            }
+            if actor.speed_condition.is_some() {
+                actor.speed_condition_turns -= 1;
+                if actor.speed_condition_turns <= 0 {
+                    actor.speed_condition = None;
+                    if visible {
+                        events.push(GameEvent::SpeedReturnedToNormal { actor_id: actor.id });
+                    }
                 }
-            },
-            _ => (),
-        };
+            }
+        }
+        loop {
+            // Ties go to whoever came first
+            let next = (0..self.actors.len())
+                .filter(|&i| i != self.player_index && !self.actors[i].is_dead && self.actors[i].energy >= ACTION_COST)
+                .max_by_key(|&i| (self.actors[i].energy, Reverse(i)));
+            let Some(index) = next else {
+                break;
+            };
+            self.actors[index].energy -= ACTION_COST;
+            events.append(&mut self.run_monster_turn(index));
+        }
+        events
+    }
+
+    fn update_misc_entity(&mut self, index: usize) -> Vec<GameEvent> {
+        let mut events = vec![];
+        if self.misc_entities[index].entity_type == MiscEntityType::SteelThistle {
+            self.misc_entities[index].data += 1;
+            if self.misc_entities[index].data > STEEL_THISTLE_CYCLE_MAX {
+                self.misc_entities[index].data = 0;
+            }
+            match self.misc_entities[index].data {
+                0..STEEL_THISTLE_CYCLE_MAX => (),
+                STEEL_THISTLE_CYCLE_MAX => {
+                    // Strike actors
+                    for actor_index in self.find_actors_at(self.misc_entities[index].position, false) {
+                        self.save_state_before_visual_effects();
+                        self.visual_effects.push(VisualEffect::ThistleStrike { position: self.misc_entities[index].position });
+                        self.modify_hp(actor_index, -1);
+                        events.push(GameEvent::SteelThistleHit { actor_id: self.actors[actor_index].id, damage: 1 });
+                        if self.actors[actor_index].is_dead {
+                            events.append(&mut self.handle_death(actor_index));
+                        }
+                    }
+                },
+                _ => unreachable!(),
+            }
+        }
+        events
+    }
+
+    fn regenerate_player(&mut self, turn: u32) {
+        let player = self.get_player();
+        let interval = (BASE_REGENERATION_INTERVAL - 2 * player.level).max(MIN_REGENERATION_INTERVAL);
+        if !player.is_dead && turn.is_multiple_of(interval as u32) {
+            self.modify_hp(self.player_index, 1);
+        }
+    }
+
+    fn update_hunger(&mut self, turn: u32) -> Vec<GameEvent> {
+        let mut events = vec![];
+        let player = self.get_player_mut();
+        let old_state = get_hunger_state(player.satiety);
+        player.satiety = (player.satiety - 1).max(0);
+        let new_state = get_hunger_state(player.satiety);
+        if new_state != old_state {
+            events.push(GameEvent::HungerChanged { actor_id: player.id, hunger: new_state });
+        }
+        if new_state == HungerState::Starving && turn.is_multiple_of(STARVATION_DAMAGE_INTERVAL) {
+            let actor_id = player.id;
+            self.modify_hp(self.player_index, -1);
+            events.push(GameEvent::StarvationDamage { actor_id, damage: 1 });
+            if self.get_player().is_dead {
+                events.append(&mut self.handle_death(self.player_index));
+            }
+        }
         events
     }
 
+    // Any monster in view, awake or not, is reason enough to stay alert
+    fn is_monster_visible(&self) -> bool {
+        self.actors.iter().any(|actor| actor.has_awareness() && !actor.is_dead && self.visible.contains(&actor.position))
+    }
+
+    fn update_terrain(&mut self) -> Vec<GameEvent> {
+        let mut any_visible_melted = false;
+        for x in 0..self.size.x { for y in 0..self.size.y {
+            let cell = &mut self.cells[x][y];
+            if cell.cell_type != CellType::Ice {
+                continue;
+            }
+            cell.timer -= 1;
+            if cell.timer <= 0 {
+                cell.cell_type = CellType::Water;
+                cell.timer = 0;
+                if self.visible.contains(&vec2(x as i32, y as i32)) {
+                    any_visible_melted = true;
+                }
+            }
+        }}
+        if any_visible_melted {
+            vec![ GameEvent::IceMelted ]
+        } else {
+            vec![]
+        }
+    }
+
     fn update_visible_and_explored(&mut self) {
         self.visible.clear();
 
@@ -554,30 +1156,65 @@ impl Room {
                 for p in orthogonal_line(player_pos, current).into_iter() {
                     self.visible.insert(p);
                     self.explored.insert(p);
-                    let cell_type = self.get_cell_type(p);
-                    match cell_type {
-                        CellType::OutOfBounds | CellType::DefaultWall | CellType::RoomExit => break,
-                        _ => (),
-                    };
+                    // The player can always see out of their own cell, e.g. when standing in thyme
+                    if p != player_pos && get_terrain_data(self.get_cell_type(p)).blocks_sight {
+                        break;
+                    }
                 }
             }
         }
     }
 
+    // Run at the end of every command, so whatever's remembered for a tile out of sight is what was on it the last
+    // time the player looked
+    pub fn update_memory(&mut self, turn: u32) {
+        let visible = self.visible.iter().copied().collect::<Vec<TilePoint>>();
+        for position in visible {
+            let actor = self.find_actors_at(position, false).into_iter()
+                .find(|&i| i != self.player_index)
+                .map(|i| RememberedActor { actor_type: self.actors[i].actor_type, turn_seen: turn });
+            let item_type = self.find_loose_items_at(position).first().map(|&i| self.items[i].item_type);
+            let misc_entity = self.find_misc_entities_at(position).first().map(|&i| self.misc_entities[i].clone());
+            if actor.is_none() && item_type.is_none() && misc_entity.is_none() {
+                self.memory.remove(&position);
+            } else {
+                self.memory.insert(position, TileMemory { actor, item_type, misc_entity });
+            }
+        }
+    }
+
     fn teleport_actor(&mut self, actor_index: usize, new_position: TilePoint) -> Vec<GameEvent> {
         let mut events = vec![];
         self.actors[actor_index].position = new_position;
+        let inventory = if actor_index == self.player_index { &self.player_inventory } else { &self.actors[actor_index].inventory };
+        for item in self.items.iter_mut() {
+            if inventory.contains(&item.id) {
+                item.position = new_position;
+            }
+        }
         if actor_index == self.player_index {
-            for item in self.items.iter_mut() {
-                if self.player_inventory.contains(&item.id) {
-                    item.position = new_position;
+            self.update_visible_and_explored();
+        } else {
+            let picks_up = get_base_stats(self.actors[actor_index].actor_type).picks_up;
+            for item_index in self.find_loose_items_at(new_position) {
+                if !picks_up.contains(&get_item_data(self.items[item_index].item_type).category) {
+                    continue;
+                }
+                let item_id = self.items[item_index].id;
+                self.items[item_index].carried = true;
+                let inventory = self.actors[actor_index].inventory.clone();
+                if self.merge_into_stack(item_id, &inventory).is_none() {
+                    self.actors[actor_index].inventory.push(item_id);
+                }
+                if self.visible.contains(&new_position) {
+                    events.push(GameEvent::PickedUpItem { actor_id: self.actors[actor_index].id, item_id });
                 }
             }
-            self.update_visible_and_explored();
         }
         let entered_cell_type = self.cells[new_position.x as usize][new_position.y as usize].cell_type;
-        if entered_cell_type == CellType::Water {
-            self.actors[actor_index].skip_next_turn = true;
+        if get_terrain_data(entered_cell_type).slows && !get_base_stats(self.actors[actor_index].actor_type).swims {
+            // Wading costs a second action
+            self.actors[actor_index].energy -= ACTION_COST;
             events.push(GameEvent::SlowedByWater { actor_id: self.actors[actor_index].id });
         }
         if actor_index == self.player_index {
@@ -585,7 +1222,7 @@ impl Room {
                 if self.misc_entities[entity_index].entity_type == MiscEntityType::HealingFont
                     && self.misc_entities[entity_index].data == 0
                 {
-                    let max_hp = self.actors[actor_index].max_hp;
+                    let max_hp = self.actors[actor_index].max_hp();
                     self.modify_hp(actor_index, max_hp);
                     self.misc_entities[entity_index].data = 1;
                     events.push(GameEvent::UsedHealingFont { actor_id: self.actors[actor_index].id });
@@ -603,7 +1240,7 @@ impl Room {
             CellType::DefaultWall | CellType::OutOfBounds => return false,
             _ => {},
         };
-        if self.find_actors_at(next_position, false).len() > 0 {
+        if !self.find_actors_at(next_position, false).is_empty() {
             return false;
         }
         true
@@ -623,16 +1260,21 @@ impl Room {
             },
             _ => {},
         };
-        if self.find_actors_at(next_position, false).len() > 0 {
+        if !self.find_actors_at(next_position, false).is_empty() {
             return WalkResult {
                 succeeded: false,
                 events: vec![],
             };
         }
-        let events = self.teleport_actor(actor_index, next_position);
+        let mut events = self.teleport_actor(actor_index, next_position);
+        if get_terrain_data(next_cell_type).slippery && self.can_actor_walk(actor_index, delta) {
+            // Slide one extra tile in the same direction
+            events.push(GameEvent::SlidOnIce { actor_id: self.actors[actor_index].id });
+            events.append(&mut self.teleport_actor(actor_index, next_position + delta));
+        }
         WalkResult {
             succeeded: true,
-            events: events,
+            events,
         }
     }
 
@@ -649,15 +1291,7 @@ impl Room {
         }
 
         self.get_item_mut(item_id).equipped = true;
-        if let Some(attack_bonus) = item_data.attack_bonus {
-            self.get_player_mut().attack_power += attack_bonus;
-        }
-        if let Some(defense_bonus) = item_data.defense_bonus {
-            self.get_player_mut().defense_power += defense_bonus;
-        }
-        if let Some(max_hp_bonus) = item_data.max_hp_bonus {
-            self.get_player_mut().max_hp += max_hp_bonus;
-        }
+        self.update_gear_bonus();
 
         events.push(GameEvent::EquippedItem { item_id });
         events
@@ -665,25 +1299,26 @@ impl Room {
 
     fn unequip_item(&mut self, item_id: u32) -> GameEvent {
         self.get_item_mut(item_id).equipped = false;
-        let item_data = self.get_item_data(item_id);
-        if let Some(attack_bonus) = item_data.attack_bonus {
-            self.get_player_mut().attack_power -= attack_bonus;
-        }
-        if let Some(defense_bonus) = item_data.defense_bonus {
-            self.get_player_mut().defense_power -= defense_bonus;
-        }
-        if let Some(max_hp_bonus) = item_data.max_hp_bonus {
-            let player = self.get_player_mut();
-            player.max_hp -= max_hp_bonus;
-            if player.current_hp > player.max_hp {
-                player.current_hp = player.max_hp;
+        self.update_gear_bonus();
+        let player = self.get_player_mut();
+        player.current_hp = player.current_hp.min(player.max_hp());
+        GameEvent::UnequippedItem { item_id }
+    }
+
+    // Only the player can equip items
+    fn update_gear_bonus(&mut self) {
+        let mut gear = StatBonus::default();
+        for &item_id in self.player_inventory.iter() {
+            if self.get_item(item_id).equipped {
+                gear.add(get_item_stat_bonus(self.get_item_data(item_id)));
             }
         }
-        GameEvent::UnequippedItem { item_id }
+        self.get_player_mut().stats.gear = gear;
     }
 
-    fn eat_item(&mut self, item_id: u32) -> Vec<GameEvent> {
+    fn eat_item(&mut self, item_id: u32, hunger_enabled: bool) -> Vec<GameEvent> {
         let mut events = vec![ GameEvent::AteItem { item_id } ];
+        let nutrition = self.get_item_data(item_id).nutrition;
         match self.get_item(item_id).item_type {
             ItemType::Bloodflower => {
                 self.modify_hp(self.player_index, 16);
@@ -691,18 +1326,40 @@ impl Room {
                 self.destroy_item(item_id);
             },
             ItemType::ElephantFrond => {
-                self.get_player_mut().attack_power += 1;
+                self.get_player_mut().stats.buffs.attack += 1;
                 events.push(GameEvent::EffectStronger { actor_id: self.get_player().id });
                 self.destroy_item(item_id);
             },
             ItemType::ArmadilloFlower => {
-                self.get_player_mut().max_hp += 2;
+                self.get_player_mut().stats.buffs.max_hp += 2;
                 self.get_player_mut().current_hp += 2;
                 events.push(GameEvent::EffectTougher { actor_id: self.get_player().id });
                 self.destroy_item(item_id);
             },
+            ItemType::Darkberry => {
+                self.modify_hp(self.player_index, 4);
+                events.push(GameEvent::EffectHealed { actor_id: self.get_player().id });
+                self.destroy_item(item_id);
+            },
+            ItemType::Acorn => {
+                self.modify_hp(self.player_index, 2);
+                events.push(GameEvent::EffectHealed { actor_id: self.get_player().id });
+                self.destroy_item(item_id);
+            },
+            ItemType::Carcass => {
+                self.destroy_item(item_id);
+            },
             _ => return vec![ GameEvent::ItemNotEdible { item_id } ],
         };
+        if let Some(nutrition) = nutrition && hunger_enabled {
+            let player = self.get_player_mut();
+            let old_state = get_hunger_state(player.satiety);
+            player.satiety = (player.satiety + nutrition).min(MAX_SATIETY);
+            let new_state = get_hunger_state(player.satiety);
+            if new_state != old_state {
+                events.push(GameEvent::HungerChanged { actor_id: player.id, hunger: new_state });
+            }
+        }
         events
     }
 
@@ -714,7 +1371,10 @@ impl Room {
                 let mut new_events = vec![];
                 new_events.push(GameEvent::EffectIceDamage { actor_id: self.actors[actor_index].id, damage });
                 if self.actors[actor_index].is_dead {
-                    new_events.push(GameEvent::Death { actor_id: self.actors[actor_index].id });
+                    new_events.append(&mut self.handle_death(actor_index));
+                } else {
+                    self.actors[actor_index].set_speed_condition(SpeedCondition::Slowed, CHILLED_TURNS);
+                    new_events.push(GameEvent::EffectSlowed { actor_id: self.actors[actor_index].id });
                 }
                 new_events
             },
@@ -724,7 +1384,17 @@ impl Room {
                 let mut new_events = vec![];
                 new_events.push(GameEvent::ThrownStoneDamage { actor_id: self.actors[actor_index].id, damage });
                 if self.actors[actor_index].is_dead {
-                    new_events.push(GameEvent::Death { actor_id: self.actors[actor_index].id });
+                    new_events.append(&mut self.handle_death(actor_index));
+                }
+                new_events
+            },
+            ItemType::Javelin => {
+                let damage = roll_physical_damage(JAVELIN_DAMAGE, self.deterministic);
+                self.modify_hp(actor_index, -damage);
+                let mut new_events = vec![];
+                new_events.push(GameEvent::JavelinDamage { actor_id: self.actors[actor_index].id, damage });
+                if self.actors[actor_index].is_dead {
+                    new_events.append(&mut self.handle_death(actor_index));
                 }
                 new_events
             },
@@ -732,32 +1402,189 @@ impl Room {
         }
     }
 
-    fn activate_item_by_direction(&mut self, item_id: u32, direction: TileDelta) -> Vec<GameEvent> {
-        assert_eq!(1, (direction.x + direction.y).abs());
-        let mut events = vec![ GameEvent::ActivatedItem { item_id } ];
+    pub fn add_to_player_inventory(&mut self, item_id: u32) {
+        let inventory = self.player_inventory.clone();
+        if self.merge_into_stack(item_id, &inventory).is_some() {
+            self.get_item_mut(item_id).carried = true;
+            return;
+        }
+        // Lowest free letter, or none once they've all been used
+        let letter = ('a'..='z').find(|&c| {
+            !self.player_inventory.iter().any(|&id| self.get_item(id).inventory_letter == Some(c))
+        });
+        let item = self.get_item_mut(item_id);
+        item.carried = true;
+        item.inventory_letter = letter;
+        self.player_inventory.push(item_id);
+    }
 
+    // Adds the item to a stack of the same type among stack_ids, if it's stackable and there is one. The item
+    // itself is used up, but keeps its quantity so events about it still read right.
+    fn merge_into_stack(&mut self, item_id: u32, stack_ids: &[u32]) -> Option<u32> {
         let item_type = self.get_item(item_id).item_type;
-        if item_type != ItemType::WandOfIce && item_type != ItemType::LumpOfBlackstone {
-            events.push(GameEvent::NoEffect { item_id });
-            return events;
+        if !get_item_data(item_type).stackable {
+            return None;
         }
+        let stack_id = stack_ids.iter().copied().find(|&id| {
+            let other_item = self.get_item(id);
+            id != item_id && other_item.item_type == item_type && !other_item.destroyed
+        })?;
+        let quantity = self.get_item(item_id).quantity;
+        self.get_item_mut(stack_id).quantity += quantity;
+        self.get_item_mut(item_id).destroyed = true;
+        Some(stack_id)
+    }
 
-        let mut current_position = self.get_player().position;
-        loop {
-            current_position += direction;
-            let other_actors = self.find_actors_at(current_position, false);
-            if other_actors.len() > 0 {
-                events.append(&mut self.apply_item_to_actor(item_id, other_actors[0]));
+    fn merge_into_floor_stack(&mut self, item_id: u32) {
+        let position = self.get_item(item_id).position;
+        let floor_ids = self.find_loose_items_at(position).into_iter().map(|i| self.items[i].id).collect::<Vec<u32>>();
+        self.merge_into_stack(item_id, &floor_ids);
+    }
+
+    // Takes some off a stack in an actor's inventory as an item of its own, also in that inventory but without a
+    // letter. Returns the original item if that's the whole stack.
+    fn split_stack(&mut self, owner_index: usize, item_id: u32, quantity: i32) -> u32 {
+        assert!(quantity >= 1);
+        let item = self.get_item(item_id);
+        if quantity >= item.quantity {
+            return item_id;
+        }
+        let mut split_item = item.clone();
+        split_item.quantity = quantity;
+        split_item.equipped = false;
+        split_item.inventory_letter = None;
+        self.get_item_mut(item_id).quantity -= quantity;
+        let split_id = self.clone_item(&split_item);
+        if owner_index == self.player_index {
+            self.player_inventory.push(split_id);
+        } else {
+            self.actors[owner_index].inventory.push(split_id);
+        }
+        split_id
+    }
+
+    // Takes an item out of an actor's hands, e.g. to throw it
+    fn remove_from_inventory(&mut self, actor_index: usize, item_id: u32) -> Vec<GameEvent> {
+        let mut events = vec![];
+        if actor_index == self.player_index {
+            if self.get_item(item_id).equipped {
+                events.push(self.unequip_item(item_id));
+            }
+            self.player_inventory.retain(|&id| id != item_id);
+            self.get_item_mut(item_id).inventory_letter = None;
+        } else {
+            self.actors[actor_index].inventory.retain(|&id| id != item_id);
+        }
+        events
+    }
+
+    // Sends an item, or whatever the item fires, flying in a straight line from an actor. The player and monsters
+    // share this for thrown items and wand beams alike.
+    pub fn fire_projectile(&mut self, shooter_index: usize, item_id: u32, direction: TileDelta, projectile: &ProjectileData) -> Vec<GameEvent> {
+        self.save_state_before_visual_effects();
+        let item_type = self.get_item(item_id).item_type;
+        let mut path = vec![];
+        let mut hit_indices = vec![];
+        let mut events = vec![];
+        let mut froze_water = false;
+        let mut current_position = self.actors[shooter_index].position;
+        for _ in 0..projectile.range {
+            let next_position = current_position + direction;
+            let cell_type = self.get_cell_type(next_position);
+            if matches!(cell_type, CellType::DefaultWall | CellType::OutOfBounds) {
                 break;
             }
-            let cell_type = self.get_cell_type(current_position);
-            match cell_type {
-                CellType::DefaultWall | CellType::OutOfBounds => break,
-                _ => {},
+            current_position = next_position;
+            path.push(current_position);
+            if item_type == ItemType::WandOfIce && (cell_type == CellType::Water || cell_type == CellType::Ice) {
+                // Refreezing ice just resets its timer
+                if cell_type == CellType::Water && !froze_water {
+                    froze_water = true;
+                    events.push(GameEvent::WaterFroze);
+                }
+                self.freeze_cell(current_position);
+            }
+            if let Some(&hit_index) = self.find_actors_at(current_position, false).first() {
+                hit_indices.push(hit_index);
+                if !projectile.piercing {
+                    break;
+                }
+            }
+        }
+        if projectile.blast_radius > 0 {
+            for i in 0..self.actors.len() {
+                let actor = &self.actors[i];
+                if i != shooter_index && !actor.is_dead && !hit_indices.contains(&i)
+                        && distance(actor.position, current_position) <= projectile.blast_radius {
+                    hit_indices.push(i);
+                }
+            }
+        }
+
+        self.visual_effects.push(VisualEffect::Projectile { item_type, path });
+        for &hit_index in hit_indices.iter() {
+            self.visual_effects.push(VisualEffect::Hit { position: self.actors[hit_index].position });
+            events.append(&mut self.apply_item_to_actor(item_id, hit_index));
+            if shooter_index == self.player_index {
+                if self.actors[hit_index].is_dead {
+                    events.append(&mut self.reward_kill(hit_index));
+                }
+                self.alert_to_player(hit_index);
+            }
+        }
+        if !hit_indices.is_empty() {
+            events.append(&mut self.make_noise(current_position, COMBAT_NOISE_RADIUS));
+        }
+
+        match projectile.landing {
+            ProjectileLanding::Drops => {
+                // Can be picked up and thrown again
+                events.append(&mut self.remove_from_inventory(shooter_index, item_id));
+                let item = self.get_item_mut(item_id);
+                item.carried = false;
+                item.position = current_position;
+                self.merge_into_floor_stack(item_id);
+            },
+            ProjectileLanding::Shatters => {
+                events.append(&mut self.remove_from_inventory(shooter_index, item_id));
+                self.get_item_mut(item_id).destroyed = true;
+            },
+            ProjectileLanding::Dissipates => {},
+        }
+        events
+    }
+
+    fn activate_item_by_direction(&mut self, item_id: u32, direction: TileDelta) -> Vec<GameEvent> {
+        assert_eq!(1, (direction.x + direction.y).abs());
+        // Only one of a stack gets thrown
+        let item_id = if get_projectile_data(self.get_item(item_id).item_type).is_some() {
+            self.split_stack(self.player_index, item_id, 1)
+        } else {
+            item_id
+        };
+        let mut events = vec![ GameEvent::ActivatedItem { item_id } ];
+
+        let item_type = self.get_item(item_id).item_type;
+        if item_type == ItemType::MoonlightKnife {
+            let target_position = self.get_player().position + direction;
+            let plants = self.find_actors_at(target_position, false).into_iter()
+                .filter(|&i| get_harvest_data(self.actors[i].actor_type).is_some())
+                .collect::<Vec<usize>>();
+            if plants.is_empty() {
+                events.push(GameEvent::NoEffect { item_id });
+            } else {
+                events.append(&mut self.harvest(self.player_index, plants[0], true));
             }
+            return events;
         }
-        let charges = self.get_item(item_id).wand_charges;
-        if let Some(charges) = charges {
+        let Some(projectile) = get_projectile_data(item_type) else {
+            events.push(GameEvent::NoEffect { item_id });
+            return events;
+        };
+
+        let player_index = self.player_index;
+        events.append(&mut self.fire_projectile(player_index, item_id, direction, projectile));
+        if let Some(charges) = self.get_item(item_id).wand_charges {
             let new_charges = charges - 1;
             if new_charges == 0 {
                 self.destroy_item(item_id);
@@ -765,19 +1592,26 @@ impl Room {
             } else {
                 self.get_item_mut(item_id).wand_charges = Some(new_charges);
             }
-        } else {
-            self.destroy_item(item_id);
         }
 
         events
     }
 }
 
+// How much an item adds to the player's stats while equipped
+pub fn get_item_stat_bonus(item_data: &ItemData) -> StatBonus {
+    StatBonus {
+        max_hp: item_data.max_hp_bonus.unwrap_or(0),
+        attack: item_data.attack_bonus.unwrap_or(0),
+        defense: item_data.defense_bonus.unwrap_or(0),
+    }
+}
+
 fn create_blank_room(size: TileSize) -> Room {
     let mut room = Room::new(size, 0);
     for x in 0..size.x {
         for y in 0..size.y {
-            if x == 0 || y == 0 || x as usize == size.x - 1 || y as usize == size.y - 1 {
+            if x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1 {
                 room.cells[x][y].cell_type = CellType::DefaultWall;
             } else {
                 room.cells[x][y].cell_type = CellType::DefaultFloor;
@@ -787,13 +1621,15 @@ fn create_blank_room(size: TileSize) -> Room {
     room
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub enum Command {
     Wait,
     Walk { delta: TileDelta },
     Fight { delta: TileDelta },
-    GetItem { item_id: u32 },
-    DropItem { item_id: u32 },
+    // Everything picked up at once takes a single turn, like auto-pickup riding along with a step
+    GetItems { item_ids: Vec<u32> },
+    // Quantity is for splitting stacks, None drops all of it
+    DropItem { item_id: u32, quantity: Option<i32> },
     ToggleEquipment { item_id: u32 },
     EatItem { item_id: u32 },
     ActivateItemByDirection { item_id: u32, direction: TileDelta },
@@ -804,6 +1640,23 @@ pub struct GameInstance {
     pub current_room: Room,
     pub event_log: Vec<GameEvent>,
     pub game_over: bool,
+    // Game mode option, on for playtests that want the pressure of finding food
+    pub hunger_enabled: bool,
+    // Loose items of these categories are picked up just by walking onto them
+    pub auto_pickup: HashSet<ItemCategory>,
+    // Frontends that don't animate can turn this off to save keeping the room from before each visual effect
+    pub animations_enabled: bool,
+}
+
+// Maps an interior position of one room to the same relative position in another, so leaving by the east
+// edge puts the player near the west edge of the next room whatever its size.
+fn scale_into_interior(position: TilePoint, from_size: TileSize, to_size: TileSize) -> TilePoint {
+    let scale_axis = |p: i32, from_len: usize, to_len: usize| {
+        let from_inner = (from_len as i32 - 3).max(1);
+        let to_inner = to_len as i32 - 3;
+        1 + ((p - 1) * to_inner + from_inner / 2) / from_inner
+    };
+    vec2(scale_axis(position.x, from_size.x, to_size.x), scale_axis(position.y, from_size.y, to_size.y))
 }
 
 impl GameInstance {
@@ -818,11 +1671,17 @@ impl GameInstance {
             },
             event_log: vec![],
             game_over: false,
+            hunger_enabled: false,
+            auto_pickup: HashSet::new(),
+            animations_enabled: true,
         }
     }
 
     pub fn create_first_room(&mut self) {
-        self.current_room = Room::generate(None, RoomGenerationConfig { depth: 0, size: vec2(19, 11) });
+        let mut rng = rand::rng();
+        let size = generate::roll_room_size(&mut rng, 0);
+        let strategy = generate::roll_strategy(&mut rng, 0);
+        self.current_room = Room::generate(None, RoomGenerationConfig { depth: 0, size, strategy });
     }
 
     fn change_rooms(&mut self, player_start: TilePoint) {
@@ -831,13 +1690,13 @@ impl GameInstance {
         let config = self.current_room.exits.get(&player_pos)
             .expect("change_rooms called but player not on exit");
 
-        let mut config = config.clone();
-        config.depth += 1;
+        let config = config.clone();
         if config.depth >= 16 {
             self.event_log.push(GameEvent::Winner);
             self.game_over = true;
             return;
         }
+        let player_start = scale_into_interior(player_start, self.current_room.size, config.size);
         let mut new_room = Room::generate(Some(player_start), config);
 
         new_room.clone_actor(self.current_room.get_player());
@@ -852,6 +1711,10 @@ impl GameInstance {
     }
 
     pub fn execute_command(&mut self, command: Command) {
+        self.current_room.visual_effects.clear();
+        self.current_room.before_visual_effects = None;
+        self.current_room.keep_state_before_visual_effects = self.animations_enabled;
+        let first_event_index = self.event_log.len();
         let turn_ended = match command {
             Command::Wait => true,
             Command::Walk { delta } => {
@@ -861,7 +1724,13 @@ impl GameInstance {
                 if result.succeeded {
                     let player_pos = self.current_room.get_player().position;
                     for i in self.current_room.find_loose_items_at(player_pos) {
-                        self.event_log.push(GameEvent::ItemIsHere { item_id: self.current_room.items[i].id });
+                        let item_id = self.current_room.items[i].id;
+                        if self.auto_pickup.contains(&self.current_room.get_item_data(item_id).category) {
+                            self.current_room.add_to_player_inventory(item_id);
+                            self.event_log.push(GameEvent::GotItem { item_id });
+                        } else {
+                            self.event_log.push(GameEvent::ItemIsHere { item_id });
+                        }
                     }
                     if self.current_room.exits.contains_key(&self.current_room.get_player().position) {
                         // Use the player's previous position as the next room start. Otherwise the
@@ -874,25 +1743,38 @@ impl GameInstance {
             Command::Fight { delta } => {
                 let attack_position = self.current_room.get_player().position + delta;
                 let other_actors = self.current_room.find_actors_at(attack_position, false);
-                if other_actors.len() > 0 {
+                if !other_actors.is_empty() {
                     let defender_index = other_actors[0];
-                    self.event_log.append(&mut self.current_room.melee_attack(self.current_room.player_index, defender_index));
+                    let player_index = self.current_room.player_index;
+                    if get_harvest_data(self.current_room.actors[defender_index].actor_type).is_some() {
+                        self.event_log.append(&mut self.current_room.harvest(player_index, defender_index, false));
+                    } else {
+                        self.event_log.append(&mut self.current_room.melee_attack(player_index, defender_index));
+                    }
                 }
                 true
             },
-            Command::GetItem { item_id } => {
-                self.current_room.get_item_mut(item_id).carried = true;
-                self.current_room.player_inventory.push(item_id);
-                self.event_log.push(GameEvent::GotItem { item_id });
+            Command::GetItems { item_ids } => {
+                for item_id in item_ids {
+                    self.current_room.add_to_player_inventory(item_id);
+                    self.event_log.push(GameEvent::GotItem { item_id });
+                }
                 true
             },
-            Command::DropItem { item_id } => {
+            Command::DropItem { item_id, quantity } => {
+                let item_id = match quantity {
+                    Some(quantity) => self.current_room.split_stack(self.current_room.player_index, item_id, quantity),
+                    None => item_id,
+                };
                 if self.current_room.get_item(item_id).equipped {
                     self.event_log.push(self.current_room.unequip_item(item_id));
                 }
-                self.current_room.get_item_mut(item_id).carried = false;
+                let item = self.current_room.get_item_mut(item_id);
+                item.carried = false;
+                item.inventory_letter = None;
                 self.current_room.player_inventory.swap_remove(self.current_room.player_inventory.iter().position(|&id| id == item_id).unwrap());
                 self.event_log.push(GameEvent::DroppedItem { item_id });
+                self.current_room.merge_into_floor_stack(item_id);
                 true
             },
             Command::ToggleEquipment { item_id } => {
@@ -904,7 +1786,13 @@ impl GameInstance {
                 true
             },
             Command::EatItem { item_id } => {
-                self.event_log.append(&mut self.current_room.eat_item(item_id));
+                // Only one of a stack gets eaten
+                let item_id = if self.current_room.get_item_data(item_id).category == ItemCategory::Consumable {
+                    self.current_room.split_stack(self.current_room.player_index, item_id, 1)
+                } else {
+                    item_id
+                };
+                self.event_log.append(&mut self.current_room.eat_item(item_id, self.hunger_enabled));
                 true
             },
             Command::ActivateItemByDirection { item_id, direction } => {
@@ -912,29 +1800,62 @@ impl GameInstance {
                 true
             },
         };
-        if turn_ended {
-            for i in 0..self.current_room.actors.len() {
-                self.event_log.append(&mut self.current_room.run_monster_turn(i));
-            }
+        if !turn_ended {
+            return;
+        }
+        self.current_room.get_player_mut().energy -= ACTION_COST;
+        // Keep the world running until the player can afford their next action. Fast players get several
+        // actions before the world moves on, slow ones sit through several turns.
+        while self.current_room.get_player().energy < ACTION_COST {
+            self.event_log.append(&mut self.current_room.run_actor_turns());
             for i in 0..self.current_room.misc_entities.len() {
                 self.event_log.append(&mut self.current_room.update_misc_entity(i));
             }
+            self.event_log.append(&mut self.current_room.update_terrain());
             self.turn += 1;
+            self.current_room.regenerate_player(self.turn);
+            if self.hunger_enabled {
+                self.event_log.append(&mut self.current_room.update_hunger(self.turn));
+            }
             if self.current_room.get_player().is_dead {
                 self.game_over = true;
                 return;
             }
-            if self.current_room.get_player().skip_next_turn {
-                self.current_room.get_player_mut().skip_next_turn = false;
-                self.execute_command(Command::Wait);
+        }
+        if self.hunger_enabled {
+            self.current_room.leave_carcasses(&self.event_log[first_event_index..]);
+        }
+        self.current_room.update_memory(self.turn);
+    }
+
+    // Waits until the player is fully healed, or until something happens that they should see
+    pub fn rest(&mut self) {
+        if self.current_room.is_monster_visible() {
+            self.event_log.push(GameEvent::CantRestNearMonsters);
+            return;
+        }
+        let start_turn = self.turn;
+        let mut turns_rested = 0;
+        while turns_rested < MAX_REST_TURNS {
+            let player = self.current_room.get_player();
+            if player.current_hp >= player.max_hp() {
+                break;
+            }
+            let event_count = self.event_log.len();
+            self.execute_command(Command::Wait);
+            turns_rested = self.turn - start_turn;
+            if self.game_over || self.event_log.len() > event_count || self.current_room.is_monster_visible() {
+                break;
             }
         }
+        self.event_log.push(GameEvent::Rested { turns: turns_rested });
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generate::GenerationStrategy;
 
     #[test]
     fn test_distance() {
@@ -1044,14 +1965,13 @@ mod tests {
         let item_id = {
             let room = &mut game.current_room;
             room.create_player(vec2(1, 1));
-            let item_id = room.create_item(ItemType::LumpOfBlackstone, vec2(1, 1));
-            item_id
+            room.create_item(ItemType::LumpOfBlackstone, vec2(1, 1))
         };
         assert!(!game.current_room.get_item(item_id).carried);
         assert_eq!(0, game.current_room.player_inventory.len());
         assert_eq!(vec![0], game.current_room.find_loose_items_at(vec2(1, 1)));
 
-        game.execute_command(Command::GetItem { item_id });
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         assert!(game.current_room.get_item(item_id).carried);
         assert_eq!(vec![item_id], game.current_room.player_inventory);
         assert_eq!(0, game.current_room.find_loose_items_at(vec2(1, 1)).len());
@@ -1060,7 +1980,7 @@ mod tests {
         // Item moves with player
         assert_eq!(vec2(2, 1), game.current_room.get_item(item_id).position);
 
-        game.execute_command(Command::DropItem { item_id });
+        game.execute_command(Command::DropItem { item_id, quantity: Some(1) });
         assert!(!game.current_room.get_item(item_id).carried);
         assert_eq!(0, game.current_room.player_inventory.len());
 
@@ -1075,12 +1995,11 @@ mod tests {
         let item_id = {
             let room = &mut game.current_room;
             room.create_player(vec2(1, 1));
-            let item_id = room.create_item(ItemType::LumpOfBlackstone, vec2(2, 1));
-            item_id
+            room.create_item(ItemType::LumpOfBlackstone, vec2(2, 1))
         };
         game.execute_command(Command::Walk { delta: vec2(1, 0) });
-        game.execute_command(Command::GetItem { item_id });
-        game.execute_command(Command::DropItem { item_id });
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::DropItem { item_id, quantity: Some(1) });
         assert_eq!(vec![
             GameEvent::ItemIsHere { item_id },
             GameEvent::GotItem { item_id },
@@ -1094,16 +2013,15 @@ mod tests {
         let item_id = {
             let room = &mut game.current_room;
             room.create_player(vec2(1, 1));
-            let item_id = room.create_item(ItemType::BlackstoneSpear, vec2(1, 1));
-            item_id
+            room.create_item(ItemType::BlackstoneSpear, vec2(1, 1))
         };
-        let attack_power_pre = game.current_room.get_player().attack_power;
-        game.execute_command(Command::GetItem { item_id });
+        let attack_power_pre = game.current_room.get_player().attack_power();
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::ToggleEquipment { item_id });
-        assert!(game.current_room.get_player().attack_power > attack_power_pre);
+        assert!(game.current_room.get_player().attack_power() > attack_power_pre);
 
         game.execute_command(Command::ToggleEquipment { item_id });
-        assert!(game.current_room.get_player().attack_power == attack_power_pre);
+        assert!(game.current_room.get_player().attack_power() == attack_power_pre);
 
         assert_eq!(vec![
             GameEvent::GotItem { item_id },
@@ -1122,8 +2040,8 @@ mod tests {
             let item_id2 = room.create_item(ItemType::BlackstoneSpear, vec2(1, 1));
             (item_id1, item_id2)
         };
-        game.execute_command(Command::GetItem { item_id: id1 });
-        game.execute_command(Command::GetItem { item_id: id2 });
+        game.execute_command(Command::GetItems { item_ids: vec![ id1 ] });
+        game.execute_command(Command::GetItems { item_ids: vec![ id2 ] });
         game.execute_command(Command::ToggleEquipment { item_id: id1 });
         game.execute_command(Command::ToggleEquipment { item_id: id2 });
         assert!(!game.current_room.get_item(id1).equipped);
@@ -1144,14 +2062,13 @@ mod tests {
         let item_id = {
             let room = &mut game.current_room;
             room.create_player(vec2(1, 1));
-            let item_id = room.create_item(ItemType::BlackstoneSpear, vec2(1, 1));
-            item_id
+            room.create_item(ItemType::BlackstoneSpear, vec2(1, 1))
         };
-        let attack_power_pre = game.current_room.get_player().attack_power;
-        game.execute_command(Command::GetItem { item_id });
+        let attack_power_pre = game.current_room.get_player().attack_power();
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::ToggleEquipment { item_id });
-        game.execute_command(Command::DropItem { item_id });
-        assert!(game.current_room.get_player().attack_power == attack_power_pre);
+        game.execute_command(Command::DropItem { item_id, quantity: Some(1) });
+        assert!(game.current_room.get_player().attack_power() == attack_power_pre);
 
         assert_eq!(vec![
             GameEvent::GotItem { item_id },
@@ -1168,10 +2085,9 @@ mod tests {
             let room = &mut game.current_room;
             room.create_player(vec2(1, 1));
             room.get_player_mut().current_hp = 1;
-            let item_id = room.create_item(ItemType::Bloodflower, vec2(1, 1));
-            item_id
+            room.create_item(ItemType::Bloodflower, vec2(1, 1))
         };
-        game.execute_command(Command::GetItem { item_id });
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::EatItem { item_id });
         assert_eq!(17, game.current_room.get_player().current_hp);
         assert_eq!(0, game.current_room.player_inventory.len());
@@ -1190,10 +2106,9 @@ mod tests {
             let room = &mut game.current_room;
             room.create_player(vec2(1, 1));
             room.get_player_mut().current_hp = 1;
-            let item_id = room.create_item(ItemType::LumpOfBlackstone, vec2(1, 1));
-            item_id
+            room.create_item(ItemType::LumpOfBlackstone, vec2(1, 1))
         };
-        game.execute_command(Command::GetItem { item_id });
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::EatItem { item_id });
         assert_eq!(1, game.current_room.get_player().current_hp);
         assert_eq!(vec![ item_id ], game.current_room.player_inventory);
@@ -1237,7 +2152,7 @@ mod tests {
         }
         game.execute_command(Command::Walk { delta: vec2(1, 0) });
         let player = game.current_room.get_player();
-        assert_eq!(player.max_hp, player.current_hp);
+        assert_eq!(player.max_hp(), player.current_hp);
         let entity_index = game.current_room.find_misc_entities_at(vec2(2, 1))[0];
         assert_eq!(1, game.current_room.misc_entities[entity_index].data);
         assert_eq!(vec![
@@ -1261,13 +2176,14 @@ mod tests {
             let monster_id = room.create_actor(ActorType::Toad, vec2(3, 1));
             (item_id, monster_id)
         };
-        let monster_max_hp = game.current_room.get_actor(monster_id).max_hp;
-        game.execute_command(Command::GetItem { item_id });
+        let monster_max_hp = game.current_room.get_actor(monster_id).max_hp();
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
         assert!(game.current_room.get_actor(monster_id).current_hp < monster_max_hp);
 
         assert_eq!(vec![
             GameEvent::GotItem { item_id },
+            GameEvent::NoticedPlayer { actor_id: monster_id },
             GameEvent::ActivatedItem { item_id },
             GameEvent::EffectIceDamage { actor_id: monster_id, damage: 8 },
             GameEvent::Death { actor_id: monster_id },
@@ -1284,7 +2200,7 @@ mod tests {
         };
         assert_eq!(Some(4), game.current_room.get_item(item_id).wand_charges);
 
-        game.execute_command(Command::GetItem { item_id });
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(-1, 0) });
         assert_eq!(Some(3), game.current_room.get_item(item_id).wand_charges);
         assert!(!game.current_room.get_item(item_id).destroyed);
@@ -1299,7 +2215,7 @@ mod tests {
             room.create_player(vec2(1, 1));
             room.create_item(ItemType::WandOfIce, vec2(1, 1))
         };
-        game.execute_command(Command::GetItem { item_id });
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         for _ in 0..4 {
             game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(-1, 0) });
         }
@@ -1320,14 +2236,14 @@ mod tests {
         let mut game = GameInstance::new();
         let (item_id, monster_id) = {
             let room = &mut game.current_room;
-            room.create_player(vec2(1, 1));
             room.set_cell(vec2(2, 1), CellType::DefaultWall);
+            room.create_player(vec2(1, 1));
             let item_id = room.create_item(ItemType::WandOfIce, vec2(1, 1));
             let monster_id = room.create_actor(ActorType::Toad, vec2(3, 1));
             (item_id, monster_id)
         };
-        let monster_max_hp = game.current_room.get_actor(monster_id).max_hp;
-        game.execute_command(Command::GetItem { item_id });
+        let monster_max_hp = game.current_room.get_actor(monster_id).max_hp();
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
         game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
         assert!(game.current_room.get_actor(monster_id).current_hp == monster_max_hp);
 
@@ -1336,4 +2252,1204 @@ mod tests {
             GameEvent::ActivatedItem { item_id },
         ], game.event_log);
     }
+
+    #[test]
+    fn test_wand_freezes_water() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.set_cell(vec2(2, 1), CellType::Water);
+            room.set_cell(vec2(3, 1), CellType::Water);
+            room.create_item(ItemType::WandOfIce, vec2(1, 1))
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
+        assert_eq!(CellType::Ice, game.current_room.get_cell_type(vec2(2, 1)));
+        assert_eq!(CellType::Ice, game.current_room.get_cell_type(vec2(3, 1)));
+        assert_eq!(vec![
+            GameEvent::GotItem { item_id },
+            GameEvent::ActivatedItem { item_id },
+            GameEvent::WaterFroze,
+        ], game.event_log);
+    }
+
+    #[test]
+    fn test_ice_melts() {
+        let mut game = GameInstance::new();
+        {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.freeze_cell(vec2(2, 1));
+        }
+        for _ in 0..(ICE_MELT_TURNS - 1) {
+            game.execute_command(Command::Wait);
+        }
+        assert_eq!(CellType::Ice, game.current_room.get_cell_type(vec2(2, 1)));
+        game.execute_command(Command::Wait);
+        assert_eq!(CellType::Water, game.current_room.get_cell_type(vec2(2, 1)));
+        assert_eq!(vec![ GameEvent::IceMelted ], game.event_log);
+    }
+
+    #[test]
+    fn test_slide_on_ice() {
+        let mut game = GameInstance::new();
+        {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.freeze_cell(vec2(2, 1));
+            room.freeze_cell(vec2(3, 2));
+            room.set_cell(vec2(3, 3), CellType::DefaultWall);
+        }
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        assert_eq!(vec2(3, 1), game.current_room.get_player().position);
+
+        // Blocked slides stop on the ice
+        game.execute_command(Command::Walk { delta: vec2(0, 1) });
+        assert_eq!(vec2(3, 2), game.current_room.get_player().position);
+
+        let player_id = game.current_room.get_player().id;
+        assert_eq!(vec![
+            GameEvent::SlidOnIce { actor_id: player_id },
+        ], game.event_log);
+    }
+
+    #[test]
+    fn test_plant_is_passive() {
+        let mut game = GameInstance::new();
+        let plant_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::BerryShrub, vec2(2, 1))
+        };
+        game.execute_command(Command::Wait);
+        let player = game.current_room.get_player();
+        assert_eq!(player.max_hp(), player.current_hp);
+        assert_eq!(vec2(2, 1), game.current_room.get_actor(plant_id).position);
+        assert_eq!(0, game.event_log.len());
+    }
+
+    #[test]
+    fn test_harvest_by_fighting() {
+        let mut game = GameInstance::new();
+        let plant_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::BloodflowerBush, vec2(2, 1))
+        };
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        let plant = game.current_room.get_actor(plant_id);
+        assert_eq!(plant.max_hp(), plant.current_hp);
+        assert!(plant.is_harvested());
+        let items = game.current_room.find_loose_items_at(vec2(1, 1));
+        assert_eq!(1, items.len());
+        let item = &game.current_room.items[items[0]];
+        assert_eq!(ItemType::Bloodflower, item.item_type);
+        assert_eq!(vec![
+            GameEvent::Harvested { actor_id: plant_id, item_id: item.id },
+        ], game.event_log);
+
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        assert_eq!(1, game.current_room.find_loose_items_at(vec2(1, 1)).len());
+        assert_eq!(GameEvent::NothingToHarvest { actor_id: plant_id }, game.event_log[1]);
+    }
+
+    #[test]
+    fn test_harvest_with_knife() {
+        let mut game = GameInstance::new();
+        let (item_id, plant_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let item_id = room.create_item(ItemType::MoonlightKnife, vec2(1, 1));
+            let plant_id = room.create_actor(ActorType::BerryShrub, vec2(1, 2));
+            (item_id, plant_id)
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(0, 1) });
+        let harvest_data = get_harvest_data(ActorType::BerryShrub).unwrap();
+        let items = game.current_room.find_loose_items_at(vec2(1, 1));
+        assert_eq!(1, items.len());
+        assert_eq!(harvest_data.tool_yield, game.current_room.items[items[0]].quantity);
+        assert!(game.current_room.get_actor(plant_id).is_harvested());
+        // The knife is not used up
+        assert_eq!(vec![item_id], game.current_room.player_inventory);
+    }
+
+    #[test]
+    fn test_plant_regrows() {
+        let mut game = GameInstance::new();
+        let plant_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::BerryShrub, vec2(2, 1))
+        };
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        let regrow_turns = get_harvest_data(ActorType::BerryShrub).unwrap().regrow_turns;
+        // The turn spent harvesting counts towards regrowing
+        for _ in 0..(regrow_turns - 2) {
+            game.execute_command(Command::Wait);
+        }
+        assert!(game.current_room.get_actor(plant_id).is_harvested());
+        game.execute_command(Command::Wait);
+        assert!(!game.current_room.get_actor(plant_id).is_harvested());
+    }
+
+    #[test]
+    fn test_thyme_blocks_sight() {
+        let mut game = GameInstance::new();
+        {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(3, 1), CellType::FloorThyme);
+            room.create_player(vec2(1, 1));
+        }
+        assert!(game.current_room.visible.contains(&vec2(3, 1)));
+        assert!(!game.current_room.visible.contains(&vec2(4, 1)));
+
+        // Standing in the thyme doesn't blind the player
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        assert!(game.current_room.visible.contains(&vec2(5, 1)));
+    }
+
+    #[test]
+    fn test_thyme_hides_player() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(1, 1), CellType::FloorThyme);
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::MouseWarrior, vec2(4, 1))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(4, 1), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_moss_gives_stealth() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(1, 1), CellType::FloorMoss);
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::MouseWarrior, vec2(6, 1))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 1), game.current_room.get_actor(monster_id).position);
+
+        // Close enough to notice the player
+        game.current_room.get_actor_mut(monster_id).position = vec2(4, 1);
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(3, 1), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_scale_into_interior() {
+        let small = vec2(19, 11);
+        let large = vec2(41, 21);
+        assert_eq!(vec2(1, 1), scale_into_interior(vec2(1, 1), small, large));
+        assert_eq!(vec2(39, 19), scale_into_interior(vec2(17, 9), small, large));
+        assert_eq!(vec2(20, 10), scale_into_interior(vec2(9, 5), small, large));
+        assert_eq!(vec2(17, 9), scale_into_interior(vec2(39, 19), large, small));
+        assert_eq!(vec2(5, 5), scale_into_interior(vec2(5, 5), small, small));
+    }
+
+    #[test]
+    fn test_sleeping_monster_does_not_move() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(4, 2));
+            room.get_actor_mut(monster_id).awareness = Awareness::Asleep;
+            monster_id
+        };
+        for _ in 0..5 {
+            game.execute_command(Command::Wait);
+        }
+        let monster = game.current_room.get_actor(monster_id);
+        assert_eq!(vec2(4, 2), monster.position);
+        assert_eq!(Awareness::Asleep, monster.awareness);
+        assert!(game.event_log.is_empty());
+    }
+
+    #[test]
+    fn test_sleeping_monster_wakes_when_adjacent() {
+        let mut game = GameInstance::new();
+        let (monster_id, player_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(2, 1));
+            room.get_actor_mut(monster_id).awareness = Awareness::Asleep;
+            (monster_id, room.get_player().id)
+        };
+        // Waking up uses the monster's turn
+        game.execute_command(Command::Wait);
+        assert_eq!(vec![ GameEvent::WokeUp { actor_id: monster_id } ], game.event_log);
+        assert_eq!(Awareness::Hunting, game.current_room.get_actor(monster_id).awareness);
+        game.execute_command(Command::Wait);
+        assert_eq!(GameEvent::MeleeAttack { attacker_id: monster_id, defender_id: player_id, damage: 7 }, game.event_log[1]);
+    }
+
+    #[test]
+    fn test_combat_noise_wakes_monsters() {
+        let mut game = GameInstance::new();
+        let (toad_id, sleeper_id) = {
+            let room = &mut game.current_room;
+            // The sleeper is out of sight around a corner
+            for y in 1..6 {
+                room.set_cell(vec2(3, y), CellType::DefaultWall);
+            }
+            room.create_player(vec2(1, 1));
+            let toad_id = room.create_actor(ActorType::Toad, vec2(1, 2));
+            let sleeper_id = room.create_actor(ActorType::MouseWarrior, vec2(5, 2));
+            room.get_actor_mut(sleeper_id).awareness = Awareness::Asleep;
+            (toad_id, sleeper_id)
+        };
+        game.execute_command(Command::Fight { delta: vec2(0, 1) });
+        assert_eq!(Awareness::Hunting, game.current_room.get_actor(toad_id).awareness);
+        let sleeper = game.current_room.get_actor(sleeper_id);
+        assert_eq!(Awareness::Searching, sleeper.awareness);
+        // Heads towards the noise instead of standing still
+        assert_eq!(vec2(4, 2), sleeper.position);
+        assert!(!game.event_log.contains(&GameEvent::WokeUp { actor_id: sleeper_id }));
+    }
+
+    #[test]
+    fn test_monster_notices_player() {
+        let mut game = GameInstance::new();
+        let (near_id, far_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let near_id = room.create_actor(ActorType::MouseWarrior, vec2(4, 2));
+            // Beyond the warrior's sight range once the player's stealth is taken off
+            let far_id = room.create_actor(ActorType::MouseWarrior, vec2(6, 6));
+            (near_id, far_id)
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec![ GameEvent::NoticedPlayer { actor_id: near_id } ], game.event_log);
+        assert_eq!(Awareness::Hunting, game.current_room.get_actor(near_id).awareness);
+        assert_eq!(vec2(3, 2), game.current_room.get_actor(near_id).position);
+        assert_eq!(Awareness::Wandering, game.current_room.get_actor(far_id).awareness);
+        assert_eq!(vec2(6, 6), game.current_room.get_actor(far_id).position);
+    }
+
+    #[test]
+    fn test_stealth_reduces_sight_range() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.get_player_mut().stealth = 5;
+            room.create_actor(ActorType::MouseWarrior, vec2(4, 1))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(Awareness::Wandering, game.current_room.get_actor(monster_id).awareness);
+        assert!(game.event_log.is_empty());
+    }
+
+    #[test]
+    fn test_monster_searches_last_known_position() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            for y in 1..6 {
+                room.set_cell(vec2(3, y), CellType::DefaultWall);
+            }
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(5, 1));
+            let monster = room.get_actor_mut(monster_id);
+            monster.awareness = Awareness::Hunting;
+            monster.search_target = vec2(5, 4);
+            monster_id
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(Awareness::Searching, game.current_room.get_actor(monster_id).awareness);
+        game.execute_command(Command::Wait);
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(5, 4), game.current_room.get_actor(monster_id).position);
+        game.execute_command(Command::Wait);
+        let monster = game.current_room.get_actor(monster_id);
+        assert_eq!(Awareness::Wandering, monster.awareness);
+        assert_eq!(vec2(5, 4), monster.position);
+    }
+
+    #[test]
+    fn test_slow_monster_acts_every_other_turn() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::BlueJelly, vec2(6, 6));
+            let monster = room.get_actor_mut(monster_id);
+            monster.awareness = Awareness::Searching;
+            monster.search_target = vec2(6, 1);
+            monster_id
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 5), game.current_room.get_actor(monster_id).position);
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 5), game.current_room.get_actor(monster_id).position);
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 4), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_fast_monster_acts_several_times_per_turn() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(6, 6));
+            room.create_actor(ActorType::ToothyStarling, vec2(1, 1))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(3, 2), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_slowed_player_acts_every_other_turn() {
+        let mut game = GameInstance::new();
+        {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.get_player_mut().set_speed_condition(SpeedCondition::Slowed, 10);
+        }
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        assert_eq!(6, game.turn);
+        assert_eq!(vec2(4, 1), game.current_room.get_player().position);
+    }
+
+    #[test]
+    fn test_wand_of_ice_slows() {
+        let mut game = GameInstance::new();
+        let (item_id, monster_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let item_id = room.create_item(ItemType::WandOfIce, vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(6, 1));
+            (item_id, monster_id)
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
+        assert!(game.event_log.contains(&GameEvent::EffectSlowed { actor_id: monster_id }));
+        assert_eq!(Some(SpeedCondition::Slowed), game.current_room.get_actor(monster_id).speed_condition);
+
+        for _ in 0..CHILLED_TURNS {
+            game.execute_command(Command::Wait);
+        }
+        assert_eq!(None, game.current_room.get_actor(monster_id).speed_condition);
+        assert!(game.event_log.contains(&GameEvent::SpeedReturnedToNormal { actor_id: monster_id }));
+    }
+
+    #[test]
+    fn test_heavy_weapon_attacks_take_longer() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(2, 1));
+            // Keep the player alive through the exchange
+            room.get_actor_mut(monster_id).stats.base.attack = 0;
+            room.create_item(ItemType::CarmineSword, vec2(1, 1))
+        };
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        assert_eq!(1, game.turn);
+
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ToggleEquipment { item_id });
+        assert_eq!(3, game.turn);
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        assert_eq!(5, game.turn);
+    }
+
+    #[test]
+    fn test_kills_give_experience() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::Toad, vec2(2, 1))
+        };
+        game.current_room.get_actor_mut(monster_id).current_hp = 1;
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        assert!(game.current_room.get_actor(monster_id).is_dead);
+        assert_eq!(get_base_stats(ActorType::Toad).experience, game.current_room.get_player().experience);
+        assert_eq!(1, game.current_room.get_player().level);
+    }
+
+    #[test]
+    fn test_level_up() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::MouseWarrior, vec2(2, 1))
+        };
+        game.current_room.get_player_mut().experience = 9;
+        game.current_room.get_actor_mut(monster_id).current_hp = 1;
+        let player_before = game.current_room.get_player().clone();
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+
+        let level_data = get_next_level_data(1).unwrap();
+        let player = game.current_room.get_player();
+        assert_eq!(2, player.level);
+        assert_eq!(player_before.max_hp() + level_data.max_hp_bonus, player.max_hp());
+        assert_eq!(player_before.attack_power() + level_data.attack_bonus, player.attack_power());
+        assert_eq!(player_before.defense_power() + level_data.defense_bonus, player.defense_power());
+        assert_eq!(vec![
+            GameEvent::MeleeAttack { attacker_id: player.id, defender_id: monster_id, damage: 1 },
+            GameEvent::Death { actor_id: monster_id },
+            GameEvent::LevelUp { actor_id: player.id, level: 2 },
+        ], game.event_log);
+    }
+
+    #[test]
+    fn test_natural_regeneration() {
+        let mut game = GameInstance::new();
+        game.current_room.create_player(vec2(1, 1));
+        game.current_room.get_player_mut().current_hp = 10;
+        let interval = BASE_REGENERATION_INTERVAL - 2;
+        for _ in 0..(interval - 1) {
+            game.execute_command(Command::Wait);
+        }
+        assert_eq!(10, game.current_room.get_player().current_hp);
+        game.execute_command(Command::Wait);
+        assert_eq!(11, game.current_room.get_player().current_hp);
+    }
+
+    #[test]
+    fn test_rest_until_healed() {
+        let mut game = GameInstance::new();
+        game.current_room.create_player(vec2(1, 1));
+        let player = game.current_room.get_player_mut();
+        player.current_hp = player.max_hp() - 2;
+        game.rest();
+        let player = game.current_room.get_player();
+        assert_eq!(player.max_hp(), player.current_hp);
+        assert_eq!(vec![ GameEvent::Rested { turns: game.turn } ], game.event_log);
+    }
+
+    #[test]
+    fn test_cant_rest_near_monsters() {
+        let mut game = GameInstance::new();
+        {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.get_player_mut().current_hp = 10;
+            room.create_actor(ActorType::Toad, vec2(5, 5));
+        }
+        game.rest();
+        assert_eq!(0, game.turn);
+        assert_eq!(vec![ GameEvent::CantRestNearMonsters ], game.event_log);
+    }
+
+    #[test]
+    fn test_hunger_disabled_by_default() {
+        let mut game = GameInstance::new();
+        game.current_room.create_player(vec2(1, 1));
+        game.execute_command(Command::Wait);
+        assert_eq!(MAX_SATIETY, game.current_room.get_player().satiety);
+    }
+
+    #[test]
+    fn test_hunger_clock() {
+        let mut game = GameInstance::new();
+        game.hunger_enabled = true;
+        game.current_room.create_player(vec2(1, 1));
+        game.current_room.get_player_mut().satiety = crate::data::HUNGRY_SATIETY + 1;
+        game.execute_command(Command::Wait);
+        let player_id = game.current_room.get_player().id;
+        assert_eq!(vec![ GameEvent::HungerChanged { actor_id: player_id, hunger: HungerState::Hungry } ], game.event_log);
+
+        game.event_log.clear();
+        game.current_room.get_player_mut().satiety = 1;
+        let hp = game.current_room.get_player().current_hp;
+        for _ in 0..STARVATION_DAMAGE_INTERVAL {
+            game.execute_command(Command::Wait);
+        }
+        assert_eq!(hp - 1, game.current_room.get_player().current_hp);
+        assert_eq!(vec![
+            GameEvent::HungerChanged { actor_id: player_id, hunger: HungerState::Starving },
+            GameEvent::StarvationDamage { actor_id: player_id, damage: 1 },
+        ], game.event_log);
+    }
+
+    #[test]
+    fn test_eating_restores_satiety() {
+        let mut game = GameInstance::new();
+        game.hunger_enabled = true;
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.get_player_mut().satiety = 0;
+            room.create_item(ItemType::Acorn, vec2(1, 1))
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.event_log.clear();
+        game.execute_command(Command::EatItem { item_id });
+        let player = game.current_room.get_player();
+        assert_eq!(get_item_data(ItemType::Acorn).nutrition.unwrap() - 1, player.satiety);
+        assert!(game.event_log.contains(&GameEvent::HungerChanged { actor_id: player.id, hunger: HungerState::Hungry }));
+    }
+
+    #[test]
+    fn test_monsters_leave_carcasses() {
+        let mut game = GameInstance::new();
+        game.hunger_enabled = true;
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::Toad, vec2(2, 1))
+        };
+        game.current_room.get_actor_mut(monster_id).current_hp = 1;
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+        let carcasses = game.current_room.find_loose_items_at(vec2(2, 1));
+        assert_eq!(1, carcasses.len());
+        assert_eq!(ItemType::Carcass, game.current_room.items[carcasses[0]].item_type);
+    }
+
+    #[test]
+    fn test_monster_drops_loot() {
+        let mut game = GameInstance::new();
+        let (monster_id, item_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(2, 1));
+            let item_id = room.give_item(room.actors.len() - 1, ItemType::BlackstoneSpear);
+            (monster_id, item_id)
+        };
+        assert!(game.current_room.find_loose_items_at(vec2(2, 1)).is_empty());
+        game.current_room.get_actor_mut(monster_id).current_hp = 1;
+        game.execute_command(Command::Fight { delta: vec2(1, 0) });
+
+        assert!(game.event_log.contains(&GameEvent::DroppedLoot { actor_id: monster_id, item_id }));
+        let item = game.current_room.get_item(item_id);
+        assert!(!item.carried);
+        assert_eq!(vec2(2, 1), item.position);
+        assert!(game.current_room.get_actor(monster_id).inventory.is_empty());
+    }
+
+    #[test]
+    fn test_monster_picks_up_items() {
+        let mut game = GameInstance::new();
+        let (monster_id, item_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(6, 6));
+            let monster = room.get_actor_mut(monster_id);
+            monster.awareness = Awareness::Searching;
+            monster.search_target = vec2(6, 2);
+            room.create_item(ItemType::Bloodflower, vec2(6, 5));
+            (monster_id, room.create_item(ItemType::Javelin, vec2(6, 5)))
+        };
+        game.execute_command(Command::Wait);
+        // Food is no use to it, so it's left behind
+        assert_eq!(vec![item_id], game.current_room.get_actor(monster_id).inventory);
+        assert_eq!(1, game.current_room.find_loose_items_at(vec2(6, 5)).len());
+        assert!(game.current_room.get_item(item_id).carried);
+        game.execute_command(Command::Wait);
+        // Carried items move with the monster
+        assert_eq!(vec2(6, 4), game.current_room.get_item(item_id).position);
+
+        // And ones of the same type go on the stack
+        let other_id = game.current_room.create_item(ItemType::Javelin, vec2(6, 3));
+        game.current_room.get_item_mut(other_id).quantity = 2;
+        game.execute_command(Command::Wait);
+        assert_eq!(vec![item_id], game.current_room.get_actor(monster_id).inventory);
+        assert_eq!(3, game.current_room.get_item(item_id).quantity);
+        assert!(game.current_room.get_item(other_id).destroyed);
+    }
+
+    #[test]
+    fn test_skirmisher_throws_javelin() {
+        let mut game = GameInstance::new();
+        let (monster_id, javelin_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseSkirmisher, vec2(5, 1));
+            room.get_actor_mut(monster_id).awareness = Awareness::Hunting;
+            (monster_id, room.give_item(room.actors.len() - 1, ItemType::Javelin))
+        };
+        game.execute_command(Command::Wait);
+        let player_id = game.current_room.get_player().id;
+        assert!(game.event_log.iter().any(|e| matches!(e, GameEvent::JavelinDamage { actor_id, .. } if *actor_id == player_id)));
+        let javelin = game.current_room.get_item(javelin_id);
+        assert!(!javelin.carried);
+        assert_eq!(vec2(1, 1), javelin.position);
+        assert!(game.current_room.get_actor(monster_id).inventory.is_empty());
+
+        // Without a javelin it closes in instead
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(4, 1), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_skirmisher_throws_one_javelin_from_stack() {
+        let mut game = GameInstance::new();
+        let (monster_id, stack_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseSkirmisher, vec2(5, 1));
+            room.get_actor_mut(monster_id).awareness = Awareness::Hunting;
+            let stack_id = room.give_item(room.actors.len() - 1, ItemType::Javelin);
+            room.get_item_mut(stack_id).quantity = 3;
+            (monster_id, stack_id)
+        };
+        game.execute_command(Command::Wait);
+        let room = &game.current_room;
+        let stack = room.get_item(stack_id);
+        assert!(stack.carried);
+        assert_eq!(2, stack.quantity);
+        assert_eq!(vec![ stack_id ], room.get_actor(monster_id).inventory);
+        let landed = room.find_loose_items_at(vec2(1, 1));
+        assert_eq!(1, landed.len());
+        assert_eq!(ItemType::Javelin, room.items[landed[0]].item_type);
+        assert_eq!(1, room.items[landed[0]].quantity);
+    }
+
+    #[test]
+    fn test_thrown_javelin_lands() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_item(ItemType::Javelin, vec2(1, 1))
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
+        let item = game.current_room.get_item(item_id);
+        assert!(!item.carried);
+        assert!(!item.destroyed);
+        assert_eq!(vec2(6, 1), item.position);
+        assert!(game.current_room.player_inventory.is_empty());
+    }
+
+    #[test]
+    fn test_toad_keeps_to_patrol() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 4));
+            room.create_actor(ActorType::Toad, vec2(1, 1))
+        };
+        let monster = game.current_room.get_actor_mut(monster_id);
+        monster.awareness = Awareness::Hunting;
+        monster.search_target = vec2(1, 4);
+        for expected in [ vec2(2, 1), vec2(2, 2), vec2(1, 2), vec2(1, 1) ] {
+            game.execute_command(Command::Wait);
+            assert_eq!(expected, game.current_room.get_actor(monster_id).position);
+        }
+    }
+
+    #[test]
+    fn test_patrol_then_pursue_rules() {
+        let mut room = create_blank_room(vec2(8, 8));
+        room.create_player(vec2(1, 5));
+        let monster_id = room.create_actor(ActorType::Toad, vec2(1, 1));
+        let index = room.actors.len() - 1;
+        const BACK_AND_FORTH: &[TileDelta] = &[ vec2(1, 0), vec2(-1, 0) ];
+        let rules = [
+            BehaviorRule { behavior: Behavior::Pursue, condition: BehaviorCondition::PlayerWithin(3) },
+            BehaviorRule { behavior: Behavior::Patrol(BACK_AND_FORTH), condition: BehaviorCondition::Always },
+        ];
+        let monster = room.get_actor_mut(monster_id);
+        monster.awareness = Awareness::Hunting;
+        monster.search_target = vec2(1, 5);
+        // Too far to chase, so it patrols
+        room.run_behavior_rules(index, &rules);
+        assert_eq!(vec2(2, 1), room.get_actor(monster_id).position);
+        room.run_behavior_rules(index, &rules);
+        assert_eq!(vec2(1, 1), room.get_actor(monster_id).position);
+
+        // Close enough to chase
+        room.teleport_actor(room.player_index, vec2(1, 4));
+        room.run_behavior_rules(index, &rules);
+        assert_eq!(vec2(1, 2), room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_skeleton_turns_at_walls() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 6));
+            room.create_actor(ActorType::DustySkeleton, vec2(5, 1))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 1), game.current_room.get_actor(monster_id).position);
+        // Bonks into the east wall and turns south
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 1), game.current_room.get_actor(monster_id).position);
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 2), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_heron_monster_moves_vertically() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(3, 4), CellType::DefaultWall);
+            room.create_player(vec2(6, 6));
+            room.create_actor(ActorType::Heron, vec2(3, 1))
+        };
+        let path = [vec2(3, 2), vec2(3, 3), vec2(3, 3), vec2(3, 2), vec2(3, 1), vec2(3, 1), vec2(3, 2)];
+        for expected in path {
+            game.execute_command(Command::Wait);
+            assert_eq!(expected, game.current_room.get_actor(monster_id).position);
+        }
+    }
+
+    #[test]
+    fn test_starlight_fairy_dazzles_player_at_range() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::StarlightFairy, vec2(3, 1))
+        };
+        game.execute_command(Command::Wait);
+        let player_id = game.current_room.get_player().id;
+        assert!(game.current_room.get_player().dazzled_turns > 0);
+        assert!(game.event_log.contains(&GameEvent::Dazzled { attacker_id: monster_id, defender_id: player_id }));
+
+        // But attacks when adjacent
+        let max_hp = game.current_room.get_player().max_hp();
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        assert!(game.current_room.get_player().current_hp < max_hp);
+
+        game.execute_command(Command::Wait);
+        game.execute_command(Command::Wait);
+        game.execute_command(Command::Wait);
+        assert_eq!(0, game.current_room.get_player().dazzled_turns);
+        assert!(game.event_log.contains(&GameEvent::DazzleFaded { actor_id: player_id }));
+    }
+
+    #[test]
+    fn test_starlight_fairy_ignores_unnoticed_player() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(1, 1), CellType::FloorThyme);
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::StarlightFairy, vec2(1, 3))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(Awareness::Wandering, game.current_room.get_actor(monster_id).awareness);
+        assert_eq!(0, game.current_room.get_player().dazzled_turns);
+    }
+
+    #[test]
+    fn test_mermaid_swims_towards_player() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(2, 1), CellType::Water);
+            room.set_cell(vec2(3, 1), CellType::Water);
+            // Hidden in thyme at first
+            room.set_cell(vec2(1, 2), CellType::FloorThyme);
+            room.create_player(vec2(1, 2));
+            room.create_actor(ActorType::Mermaid, vec2(3, 1))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(Awareness::Wandering, game.current_room.get_actor(monster_id).awareness);
+        assert_eq!(vec2(3, 1), game.current_room.get_actor(monster_id).position);
+
+        // Out in the open it notices the player and comes over
+        game.execute_command(Command::Walk { delta: vec2(0, -1) });
+        game.execute_command(Command::Wait);
+        assert_eq!(Awareness::Hunting, game.current_room.get_actor(monster_id).awareness);
+        assert_eq!(vec2(2, 1), game.current_room.get_actor(monster_id).position);
+        assert!(!game.event_log.iter().any(|e| matches!(e, GameEvent::SlowedByWater { .. })));
+    }
+
+    #[test]
+    fn test_mermaid_ignores_distant_player() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::Mermaid, vec2(6, 6))
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(6, 6), game.current_room.get_actor(monster_id).position);
+    }
+
+    #[test]
+    fn test_wounded_monster_flees() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(2, 1));
+            let monster = room.get_actor_mut(monster_id);
+            monster.awareness = Awareness::Hunting;
+            monster.current_hp = 1;
+            monster_id
+        };
+        game.execute_command(Command::Wait);
+        assert!(game.event_log.contains(&GameEvent::MonsterFled { actor_id: monster_id }));
+        assert_eq!(Awareness::Fleeing, game.current_room.get_actor(monster_id).awareness);
+        assert_eq!(game.current_room.get_player().max_hp(), game.current_room.get_player().current_hp);
+        game.execute_command(Command::Wait);
+        let player_pos = game.current_room.get_player().position;
+        assert!(distance(player_pos, game.current_room.get_actor(monster_id).position) > 1);
+    }
+
+    #[test]
+    fn test_cornered_monster_fights_back() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(1, 2), CellType::DefaultWall);
+            room.set_cell(vec2(2, 2), CellType::DefaultWall);
+            room.create_player(vec2(2, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(1, 1));
+            let monster = room.get_actor_mut(monster_id);
+            monster.awareness = Awareness::Fleeing;
+            monster.current_hp = 1;
+            monster_id
+        };
+        game.execute_command(Command::Wait);
+        assert_eq!(vec2(1, 1), game.current_room.get_actor(monster_id).position);
+        assert!(game.event_log.iter().any(|e| matches!(e, GameEvent::MeleeAttack { attacker_id, .. } if *attacker_id == monster_id)));
+    }
+
+    #[test]
+    fn test_fleeing_monster_rallies() {
+        let mut game = GameInstance::new();
+        let monster_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::MouseWarrior, vec2(12, 8));
+            let monster = room.get_actor_mut(monster_id);
+            monster.awareness = Awareness::Fleeing;
+            monster.current_hp = 1;
+            monster_id
+        };
+        let max_hp = game.current_room.get_actor(monster_id).max_hp();
+        let rally_hp = (max_hp as f32 * RALLY_HP_FRACTION).ceil() as i32;
+        let turns = (rally_hp - 1) * FLEEING_REGENERATION_INTERVAL;
+        for _ in 0..(turns - 1) {
+            game.execute_command(Command::Wait);
+        }
+        assert_eq!(Awareness::Fleeing, game.current_room.get_actor(monster_id).awareness);
+        game.execute_command(Command::Wait);
+        assert_eq!(rally_hp, game.current_room.get_actor(monster_id).current_hp);
+        assert!(game.event_log.contains(&GameEvent::MonsterRallied { actor_id: monster_id }));
+        assert_ne!(Awareness::Fleeing, game.current_room.get_actor(monster_id).awareness);
+    }
+
+    #[test]
+    fn test_thrown_item_range() {
+        let mut game = GameInstance::new();
+        game.current_room = create_blank_room(vec2(12, 3));
+        game.current_room.deterministic = true;
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_item(ItemType::Javelin, vec2(1, 1))
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
+        let range = get_projectile_data(ItemType::Javelin).unwrap().range;
+        assert_eq!(vec2(1 + range, 1), game.current_room.get_item(item_id).position);
+    }
+
+    #[test]
+    fn test_piercing_projectile() {
+        let mut room = create_blank_room(vec2(8, 3));
+        room.deterministic = true;
+        room.create_player(vec2(1, 1));
+        let player_index = room.player_index;
+        let first_id = room.create_actor(ActorType::MouseWarrior, vec2(3, 1));
+        let second_id = room.create_actor(ActorType::MouseWarrior, vec2(5, 1));
+        let item_id = room.create_item(ItemType::Javelin, vec2(1, 1));
+        let projectile = ProjectileData { range: 10, piercing: true, blast_radius: 0, landing: ProjectileLanding::Dissipates };
+        let events = room.fire_projectile(player_index, item_id, vec2(1, 0), &projectile);
+        assert_eq!(Some(&VisualEffect::Projectile {
+            item_type: ItemType::Javelin,
+            path: vec![ vec2(2, 1), vec2(3, 1), vec2(4, 1), vec2(5, 1), vec2(6, 1) ],
+        }), room.visual_effects.first());
+        for actor_id in [ first_id, second_id ] {
+            assert!(events.iter().any(|e| matches!(e, GameEvent::JavelinDamage { actor_id: hit_id, .. } if *hit_id == actor_id)));
+        }
+    }
+
+    #[test]
+    fn test_projectile_blast() {
+        let mut room = create_blank_room(vec2(8, 5));
+        room.deterministic = true;
+        room.create_player(vec2(1, 2));
+        let player_index = room.player_index;
+        let target_id = room.create_actor(ActorType::MouseWarrior, vec2(4, 2));
+        let nearby_id = room.create_actor(ActorType::MouseWarrior, vec2(4, 3));
+        let distant_id = room.create_actor(ActorType::MouseWarrior, vec2(6, 1));
+        let item_id = room.create_item(ItemType::LumpOfBlackstone, vec2(1, 2));
+        let projectile = ProjectileData { range: 10, piercing: false, blast_radius: 1, landing: ProjectileLanding::Dissipates };
+        let events = room.fire_projectile(player_index, item_id, vec2(1, 0), &projectile);
+        assert!(matches!(room.visual_effects.first(), Some(VisualEffect::Projectile { path, .. }) if path.last() == Some(&vec2(4, 2))));
+        let was_hit = |id: u32| events.iter().any(|e| matches!(e, GameEvent::ThrownStoneDamage { actor_id, .. } if *actor_id == id));
+        assert!(was_hit(target_id));
+        assert!(was_hit(nearby_id));
+        assert!(!was_hit(distant_id));
+        assert!(!was_hit(room.get_player().id));
+    }
+
+    #[test]
+    fn test_visual_effects_cover_last_command() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            room.create_actor(ActorType::BerryShrub, vec2(4, 1));
+            room.create_item(ItemType::LumpOfBlackstone, vec2(1, 1))
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
+        assert_eq!(Some(&VisualEffect::Projectile {
+            item_type: ItemType::LumpOfBlackstone,
+            path: vec![ vec2(2, 1), vec2(3, 1), vec2(4, 1) ],
+        }), game.current_room.visual_effects.first());
+        assert!(game.current_room.visual_effects.contains(&VisualEffect::Hit { position: vec2(4, 1) }));
+
+        game.execute_command(Command::Wait);
+        assert!(game.current_room.visual_effects.is_empty());
+    }
+
+    #[test]
+    fn test_visual_effects_keep_state_before_them() {
+        let mut game = GameInstance::new();
+        let (item_id, monster_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let monster_id = room.create_actor(ActorType::BerryShrub, vec2(4, 1));
+            room.get_actor_mut(monster_id).current_hp = 1;
+            (room.create_item(ItemType::Javelin, vec2(1, 1)), monster_id)
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        assert!(game.current_room.before_visual_effects.is_none());
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(1, 0) });
+        assert!(game.current_room.get_actor(monster_id).is_dead);
+        assert_eq!(1, game.current_room.find_loose_items_at(vec2(4, 1)).len());
+
+        // Still in flight and the shrub still standing
+        let before = game.current_room.before_visual_effects.as_ref().unwrap();
+        assert!(!before.get_actor(monster_id).is_dead);
+        assert!(before.find_loose_items_at(vec2(4, 1)).is_empty());
+        assert!(before.visual_effects.is_empty());
+
+        game.execute_command(Command::Wait);
+        assert!(game.current_room.before_visual_effects.is_none());
+
+        // Not kept at all without animations
+        game.animations_enabled = false;
+        game.current_room.teleport_actor(game.current_room.player_index, vec2(4, 1));
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::ActivateItemByDirection { item_id, direction: vec2(-1, 0) });
+        assert!(!game.current_room.visual_effects.is_empty());
+        assert!(game.current_room.before_visual_effects.is_none());
+    }
+
+    #[test]
+    fn test_remembers_things_out_of_sight() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.set_cell(vec2(3, 1), CellType::FloorThyme);
+            room.create_player(vec2(4, 1));
+            room.create_actor(ActorType::BerryShrub, vec2(6, 2));
+            room.create_item(ItemType::CarmineSword, vec2(5, 1))
+        };
+        game.execute_command(Command::Walk { delta: vec2(-1, 0) });
+        game.execute_command(Command::Walk { delta: vec2(-1, 0) });
+        let room = &game.current_room;
+        assert!(!room.visible.contains(&vec2(5, 1)));
+        assert_eq!(Some(ItemType::CarmineSword), room.memory[&vec2(5, 1)].item_type);
+        assert_eq!(Some(RememberedActor { actor_type: ActorType::BerryShrub, turn_seen: 1 }), room.memory[&vec2(6, 2)].actor);
+
+        // Forgotten once the player sees it's gone
+        game.current_room.get_item_mut(item_id).destroyed = true;
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        assert!(!game.current_room.memory.contains_key(&vec2(5, 1)));
+        assert_eq!(4, game.current_room.memory[&vec2(6, 2)].actor.unwrap().turn_seen);
+    }
+
+    #[test]
+    fn test_stat_sources_tracked_separately() {
+        let mut game = GameInstance::new();
+        let (sword_id, frond_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let sword_id = room.create_item(ItemType::CarmineSword, vec2(1, 1));
+            let frond_id = room.create_item(ItemType::ElephantFrond, vec2(1, 1));
+            (sword_id, frond_id)
+        };
+        let base = game.current_room.get_player().stats.base;
+        game.execute_command(Command::GetItems { item_ids: vec![ sword_id ] });
+        game.execute_command(Command::GetItems { item_ids: vec![ frond_id ] });
+        game.execute_command(Command::ToggleEquipment { item_id: sword_id });
+        game.execute_command(Command::EatItem { item_id: frond_id });
+
+        let sword_bonus = get_item_stat_bonus(get_item_data(ItemType::CarmineSword));
+        let player = game.current_room.get_player();
+        assert_eq!(base, player.stats.base);
+        assert_eq!(sword_bonus, player.stats.gear);
+        assert_eq!(1, player.stats.buffs.attack);
+        assert_eq!(base.attack + sword_bonus.attack + 1, player.attack_power());
+
+        game.execute_command(Command::ToggleEquipment { item_id: sword_id });
+        assert_eq!(StatBonus::default(), game.current_room.get_player().stats.gear);
+        assert_eq!(base.attack + 1, game.current_room.get_player().attack_power());
+    }
+
+    #[test]
+    fn test_inventory_letters_are_stable() {
+        let mut game = GameInstance::new();
+        let item_ids = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            [ ItemType::Javelin, ItemType::Acorn, ItemType::CarmineHelm ].map(|item_type| room.create_item(item_type, vec2(1, 1)))
+        };
+        for item_id in item_ids {
+            game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        }
+        let letter = |game: &GameInstance, item_id: u32| game.current_room.get_item(item_id).inventory_letter;
+        assert_eq!(Some('a'), letter(&game, item_ids[0]));
+        assert_eq!(Some('b'), letter(&game, item_ids[1]));
+        assert_eq!(Some('c'), letter(&game, item_ids[2]));
+
+        // Dropping doesn't reshuffle the others, and the gap gets filled first
+        game.execute_command(Command::DropItem { item_id: item_ids[0], quantity: Some(1) });
+        assert_eq!(None, letter(&game, item_ids[0]));
+        assert_eq!(Some('c'), letter(&game, item_ids[2]));
+        game.execute_command(Command::GetItems { item_ids: vec![ item_ids[0] ] });
+        assert_eq!(Some('a'), letter(&game, item_ids[0]));
+    }
+
+    #[test]
+    fn test_auto_pickup() {
+        let mut game = GameInstance::new();
+        game.auto_pickup.insert(ItemCategory::Consumable);
+        let (flower_id, sword_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let flower_id = room.create_item(ItemType::Bloodflower, vec2(2, 1));
+            let sword_id = room.create_item(ItemType::CarmineSword, vec2(2, 1));
+            (flower_id, sword_id)
+        };
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        assert_eq!(vec![ flower_id ], game.current_room.player_inventory);
+        assert!(game.event_log.contains(&GameEvent::GotItem { item_id: flower_id }));
+        assert!(game.event_log.contains(&GameEvent::ItemIsHere { item_id: sword_id }));
+        assert_eq!(1, game.turn);
+    }
+
+    #[test]
+    fn test_pick_up_several_items_in_one_turn() {
+        let mut game = GameInstance::new();
+        let item_ids = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            vec![ room.create_item(ItemType::Bloodflower, vec2(1, 1)), room.create_item(ItemType::CarmineSword, vec2(1, 1)) ]
+        };
+        game.execute_command(Command::GetItems { item_ids: item_ids.clone() });
+        assert_eq!(item_ids, game.current_room.player_inventory);
+        assert_eq!(1, game.turn);
+    }
+
+    #[test]
+    fn test_stacks_merge_on_pickup() {
+        let mut game = GameInstance::new();
+        let (first_id, second_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let first_id = room.create_item(ItemType::Bloodflower, vec2(1, 1));
+            let second_id = room.create_item(ItemType::Bloodflower, vec2(1, 1));
+            room.get_item_mut(second_id).quantity = 2;
+            (first_id, second_id)
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ first_id ] });
+        game.execute_command(Command::GetItems { item_ids: vec![ second_id ] });
+        assert_eq!(vec![ first_id ], game.current_room.player_inventory);
+        assert_eq!(3, game.current_room.get_item(first_id).quantity);
+        assert_eq!(Some('a'), game.current_room.get_item(first_id).inventory_letter);
+
+        // Unstackable items never merge
+        let sword_ids = [ ItemType::CarmineSword, ItemType::CarmineSword ].map(|item_type| game.current_room.create_item(item_type, vec2(1, 1)));
+        for item_id in sword_ids {
+            game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        }
+        assert_eq!(3, game.current_room.player_inventory.len());
+    }
+
+    #[test]
+    fn test_drop_splits_stack() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let item_id = room.create_item(ItemType::Acorn, vec2(1, 1));
+            room.get_item_mut(item_id).quantity = 5;
+            item_id
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::DropItem { item_id, quantity: Some(2) });
+        assert_eq!(3, game.current_room.get_item(item_id).quantity);
+        let loose = game.current_room.find_loose_items_at(vec2(1, 1));
+        assert_eq!(1, loose.len());
+        assert_eq!(2, game.current_room.items[loose[0]].quantity);
+
+        // Dropping more onto the pile adds to it
+        game.execute_command(Command::DropItem { item_id, quantity: Some(1) });
+        let loose = game.current_room.find_loose_items_at(vec2(1, 1));
+        assert_eq!(1, loose.len());
+        assert_eq!(3, game.current_room.items[loose[0]].quantity);
+        assert_eq!(vec![ item_id ], game.current_room.player_inventory);
+    }
+
+    #[test]
+    fn test_eating_and_throwing_use_one_from_stack() {
+        let mut game = GameInstance::new();
+        let (flower_id, stone_id) = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let flower_id = room.create_item(ItemType::Darkberry, vec2(1, 1));
+            let stone_id = room.create_item(ItemType::LumpOfBlackstone, vec2(1, 1));
+            room.get_item_mut(flower_id).quantity = 3;
+            room.get_item_mut(stone_id).quantity = 3;
+            (flower_id, stone_id)
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ flower_id ] });
+        game.execute_command(Command::GetItems { item_ids: vec![ stone_id ] });
+        game.execute_command(Command::EatItem { item_id: flower_id });
+        game.execute_command(Command::ActivateItemByDirection { item_id: stone_id, direction: vec2(1, 0) });
+        assert_eq!(2, game.current_room.get_item(flower_id).quantity);
+        assert_eq!(2, game.current_room.get_item(stone_id).quantity);
+        assert_eq!(vec![ flower_id, stone_id ], game.current_room.player_inventory);
+    }
+
+    #[test]
+    fn test_changing_rooms_keeps_quantities() {
+        let mut game = GameInstance::new();
+        let item_id = {
+            let room = &mut game.current_room;
+            room.create_player(vec2(1, 1));
+            let item_id = room.create_item(ItemType::Javelin, vec2(1, 1));
+            room.get_item_mut(item_id).quantity = 4;
+            room.set_cell(vec2(2, 1), CellType::RoomExit);
+            room.exits.insert(vec2(2, 1), RoomGenerationConfig { depth: 1, size: vec2(23, 13), strategy: GenerationStrategy::Tunnels });
+            item_id
+        };
+        game.execute_command(Command::GetItems { item_ids: vec![ item_id ] });
+        game.execute_command(Command::Walk { delta: vec2(1, 0) });
+        // The room is made just as the exit says
+        assert_eq!(1, game.current_room.depth);
+        assert_eq!(vec2(23, 13), game.current_room.size);
+        let room = &game.current_room;
+        assert_eq!(1, room.player_inventory.len());
+        assert_eq!(4, room.get_item(room.player_inventory[0]).quantity);
+    }
 }
@@ -5,6 +5,7 @@ use rand::prelude::IndexedRandom;
 use rand::Rng;
 
 use crate::data::{ActorType, CellType, ItemType, MiscEntityType, NEIGHBORS, TilePoint, TileSize};
+use crate::prefab::{self, Prefab};
 
 fn create_2d_vec<T: Default + Clone>(size: TileSize) -> Vec<Vec<T>> {
     let mut result = Vec::with_capacity(size.x);
@@ -25,10 +26,39 @@ const AUTOMATA_NEIGHBORS: &[(i32, i32)] = &[
     (1, 1),
 ];
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GenerationStrategy {
+    // Random fill smoothed with cellular automata
+    Caves,
+    // Binary space partitioning into rooms joined by corridors
+    RoomsAndCorridors,
+    // Drunkard's walk tunnels dug out from the player start
+    Tunnels,
+    // Caves with a few walled structures stamped on top
+    Mixed,
+}
+
 #[derive(Clone, Debug)]
 pub struct RoomGenerationConfig {
     pub depth: i32,
     pub size: TileSize,
+    pub strategy: GenerationStrategy,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RoomTheme {
+    Barren,
+    Mossy,
+    Meadow,
+    Marsh,
+}
+
+// Positions changed by the decoration pass, for testing and debugging
+#[derive(Debug, Default)]
+pub struct Decorations {
+    pub moss: Vec<TilePoint>,
+    pub thyme: Vec<TilePoint>,
+    pub water: Vec<TilePoint>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -45,43 +75,48 @@ pub struct GeneratedRoom {
     pub cells: Vec<Vec<GeneratedCell>>,
     pub exits: Vec<TilePoint>,
     pub player_start: TilePoint,
+    #[allow(unused)]
+    pub theme: RoomTheme,
+    #[allow(unused)]
+    pub decorations: Decorations,
 }
 
 fn is_navigable(cell_type: CellType) -> bool {
     match cell_type {
-        CellType::DefaultFloor | CellType::FloorMoss | CellType::FloorThyme | CellType::RoomExit | CellType::Water => true,
+        CellType::DefaultFloor | CellType::FloorMoss | CellType::FloorThyme | CellType::RoomExit | CellType::Water | CellType::Ice => true,
         CellType::OutOfBounds | CellType::DefaultWall | CellType::Empty => false,
     }
 }
 
 fn is_open(cell_type: CellType) -> bool {
-    match cell_type {
-        CellType::DefaultFloor | CellType::FloorMoss | CellType::FloorThyme => true,
-        _ => false,
-    }
+    matches!(cell_type, CellType::DefaultFloor | CellType::FloorMoss | CellType::FloorThyme)
 }
 
-fn floodfill_navigable_area_recursive_helper(room: &Vec<Vec<GeneratedCell>>, current: TilePoint, result: &mut HashSet<TilePoint>) {
-    if !is_navigable(room[current.x as usize][current.y as usize].cell_type) {
-        return;
+fn floodfill_navigable_area(room: &[Vec<GeneratedCell>], start: TilePoint) -> HashSet<TilePoint> {
+    // Exits sit on the border, so the fill can step off the map through them
+    let is_fillable = |p: TilePoint| {
+        p.x >= 0 && p.y >= 0 && (p.x as usize) < room.len() && (p.y as usize) < room[0].len()
+            && is_navigable(room[p.x as usize][p.y as usize].cell_type)
+    };
+    let mut result = HashSet::new();
+    if !is_fillable(start) {
+        return result;
     }
-    result.insert(current);
-    for &(dx, dy) in NEIGHBORS.iter() {
-        let adj = vec2(current.x + dx, current.y + dy);
-        if result.contains(&adj) {
-            continue;
+    // Explicit stack rather than recursion, large rooms would overflow the call stack
+    let mut pending = vec![start];
+    result.insert(start);
+    while let Some(current) = pending.pop() {
+        for &(dx, dy) in NEIGHBORS.iter() {
+            let adj = vec2(current.x + dx, current.y + dy);
+            if is_fillable(adj) && result.insert(adj) {
+                pending.push(adj);
+            }
         }
-        floodfill_navigable_area_recursive_helper(room, adj, result);
     }
-}
-
-fn floodfill_navigable_area(room: &Vec<Vec<GeneratedCell>>, start: TilePoint) -> HashSet<TilePoint> {
-    let mut result = HashSet::new();
-    floodfill_navigable_area_recursive_helper(room, start, &mut result);
     result
 }
 
-fn partition_navigable_areas(size: TileSize, room: &Vec<Vec<GeneratedCell>>) -> Vec<HashSet<TilePoint>> {
+fn partition_navigable_areas(size: TileSize, room: &[Vec<GeneratedCell>]) -> Vec<HashSet<TilePoint>> {
     let mut visited_points = HashSet::new();
     let mut areas = vec![];
     for x in 0..size.x { for y in 0..size.y {
@@ -93,47 +128,76 @@ fn partition_navigable_areas(size: TileSize, room: &Vec<Vec<GeneratedCell>>) ->
         for &p in area.iter() {
             visited_points.insert(p);
         }
-        if area.len() > 0 {
+        if !area.is_empty() {
             areas.push(area);
         }
     }}
     areas
 }
 
-fn connect_with_drunkards_walk(room: &mut Vec<Vec<GeneratedCell>>, current: TilePoint, end: TilePoint) {
-    let mut rng = rand::rng();
-    if current == end {
-        return;
-    }
-    let delta = if rng.random::<f32>() < 0.25 {
-        *NEIGHBORS.choose(&mut rng).unwrap()
-    } else {
-        let dx = end.x - current.x;
-        let dy = end.y - current.y;
-        if dx.abs() > dy.abs() {
-            (dx.signum(), 0)
+// Walks stay out of prefabs unless they lead into one, otherwise they get lost inside its walls
+fn is_blocked_for_walk(room: &[Vec<GeneratedCell>], p: TilePoint, end: TilePoint) -> bool {
+    p != end && room[p.x as usize][p.y as usize].immutable
+}
+
+fn connect_with_drunkards_walk(rng: &mut impl Rng, room: &mut [Vec<GeneratedCell>], start: TilePoint, end: TilePoint) {
+    let mut current = start;
+    while current != end {
+        let delta = if rng.random::<f32>() < 0.25 {
+            *NEIGHBORS.choose(rng).unwrap()
         } else {
-            (0, dy.signum())
+            let dx = end.x - current.x;
+            let dy = end.y - current.y;
+            if dx.abs() > dy.abs() {
+                (dx.signum(), 0)
+            } else {
+                (0, dy.signum())
+            }
+        };
+        let mut next = vec2(current.x + delta.0, current.y + delta.1);
+        if is_blocked_for_walk(room, next, end) {
+            // Stumble sideways so the walk can find its way around
+            let open_neighbors: Vec<TilePoint> = NEIGHBORS.iter()
+                .map(|&(dx, dy)| vec2(current.x + dx, current.y + dy))
+                .filter(|&p| !is_blocked_for_walk(room, p, end))
+                .collect();
+            match open_neighbors.choose(rng) {
+                Some(&p) => next = p,
+                None => return,
+            }
         }
-    };
-    let next = vec2(current.x + delta.0, current.y + delta.1);
-    let navigable = is_navigable(room[next.x as usize][next.y as usize].cell_type);
-    if room[next.x as usize][next.y as usize].immutable && !navigable {
-        connect_with_drunkards_walk(room, current, end);
-    } else {
-        if !navigable {
-            room[next.x as usize][next.y as usize].cell_type = CellType::DefaultFloor;
+        let cell = &mut room[next.x as usize][next.y as usize];
+        if !is_navigable(cell.cell_type) {
+            cell.cell_type = CellType::DefaultFloor;
         }
-        connect_with_drunkards_walk(room, next, end);
+        current = next;
     }
 }
 
-fn find_edge_walls(size: TileSize, room: &Vec<Vec<GeneratedCell>>) -> Vec<TilePoint> {
+// Cells of the area that touch something the generator may still carve. Walks between areas start and end
+// here so they don't get trapped inside prefab walls.
+fn find_area_frontier(room: &[Vec<GeneratedCell>], area: &HashSet<TilePoint>) -> Vec<TilePoint> {
+    let frontier: Vec<TilePoint> = area.iter().copied()
+        .filter(|p| NEIGHBORS.iter().any(|&(dx, dy)| !room[(p.x + dx) as usize][(p.y + dy) as usize].immutable))
+        .collect();
+    if frontier.is_empty() {
+        area.iter().copied().collect()
+    } else {
+        frontier
+    }
+}
+
+fn find_edge_walls(size: TileSize, room: &[Vec<GeneratedCell>]) -> Vec<TilePoint> {
     let mut result = vec![];
     for x in 0..size.x { for y in 0..size.y {
         if room[x][y].cell_type != CellType::DefaultWall {
             continue;
         }
+        // Don't knock exits through prefab walls
+        let is_border = x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1;
+        if room[x][y].immutable && !is_border {
+            continue;
+        }
         let point = vec2(x as i32, y as i32);
         let mut adjacent_floors = 0;
         for &d in NEIGHBORS.iter() {
@@ -152,19 +216,194 @@ fn find_edge_walls(size: TileSize, room: &Vec<Vec<GeneratedCell>>) -> Vec<TilePo
     result
 }
 
-fn collect_open_cells(size: TileSize, room: &Vec<Vec<GeneratedCell>>, player_start: TilePoint) -> Vec<TilePoint> {
+fn collect_open_cells(size: TileSize, room: &[Vec<GeneratedCell>], player_start: TilePoint) -> Vec<TilePoint> {
     let mut result = vec![];
+    #[allow(clippy::needless_range_loop)]
     for x in 0..size.x { for y in 0..size.y {
         let p = vec2(x as i32, y as i32);
-        if p != player_start && is_open(room[x][y].cell_type) {
+        let cell = &room[x][y];
+        let occupied = cell.monster.is_some() || cell.item.is_some() || cell.misc_entity.is_some();
+        if p != player_start && !occupied && is_open(cell.cell_type) {
             result.push(p);
         }
     }}
     result
 }
 
-fn roll_monsters(depth: i32) -> Vec<ActorType> {
-    let mut rng = rand::rng();
+fn collect_decoratable_cells(size: TileSize, room: &[Vec<GeneratedCell>]) -> Vec<TilePoint> {
+    let mut result = vec![];
+    #[allow(clippy::needless_range_loop)]
+    for x in 0..size.x { for y in 0..size.y {
+        if !room[x][y].immutable && room[x][y].cell_type == CellType::DefaultFloor {
+            result.push(vec2(x as i32, y as i32));
+        }
+    }}
+    result
+}
+
+fn can_place_flora(cell: &GeneratedCell) -> bool {
+    !cell.immutable && cell.cell_type == CellType::DefaultFloor
+}
+
+fn can_place_water(cell: &GeneratedCell) -> bool {
+    // Carving water through walls only ever adds navigable paths, so connectivity is kept.
+    !cell.immutable && (cell.cell_type == CellType::DefaultFloor || cell.cell_type == CellType::DefaultWall)
+}
+
+// Grows a patch outwards from the seed by picking random frontier cells, which gives organic shapes.
+fn grow_patch(
+    rng: &mut impl Rng,
+    size: TileSize,
+    room: &mut [Vec<GeneratedCell>],
+    seed: TilePoint,
+    target_size: usize,
+    cell_type: CellType,
+    can_place: fn(&GeneratedCell) -> bool,
+) -> Vec<TilePoint> {
+    let mut placed = vec![];
+    let mut frontier = vec![seed];
+    let mut seen = HashSet::new();
+    seen.insert(seed);
+    while placed.len() < target_size && !frontier.is_empty() {
+        let i = rng.random_range(0..frontier.len());
+        let p = frontier.swap_remove(i);
+        let cell = &mut room[p.x as usize][p.y as usize];
+        if !can_place(cell) {
+            continue;
+        }
+        cell.cell_type = cell_type;
+        placed.push(p);
+        for &(dx, dy) in NEIGHBORS.iter() {
+            let adj = vec2(p.x + dx, p.y + dy);
+            if adj.x < 0 || adj.y < 0 || adj.x as usize >= size.x || adj.y as usize >= size.y {
+                continue;
+            }
+            if seen.insert(adj) {
+                frontier.push(adj);
+            }
+        }
+    }
+    placed
+}
+
+fn carve_stream(rng: &mut impl Rng, size: TileSize, room: &mut [Vec<GeneratedCell>], start: TilePoint, end: TilePoint) -> Vec<TilePoint> {
+    let mut placed = vec![];
+    let mut current = start;
+    let max_steps = size.x * size.y * 4;
+    for _ in 0..max_steps {
+        if can_place_water(&room[current.x as usize][current.y as usize]) {
+            room[current.x as usize][current.y as usize].cell_type = CellType::Water;
+            placed.push(current);
+        }
+        if current == end {
+            break;
+        }
+        // Meander more than connect_with_drunkards_walk so streams don't look like corridors
+        let delta = if rng.random::<f32>() < 0.4 {
+            *NEIGHBORS.choose(rng).unwrap()
+        } else {
+            let dx = end.x - current.x;
+            let dy = end.y - current.y;
+            if dx.abs() > dy.abs() {
+                (dx.signum(), 0)
+            } else {
+                (0, dy.signum())
+            }
+        };
+        let next = vec2(current.x + delta.0, current.y + delta.1);
+        if next.x <= 0 || next.y <= 0 || next.x as usize >= size.x - 1 || next.y as usize >= size.y - 1 {
+            continue;
+        }
+        let next_cell = &room[next.x as usize][next.y as usize];
+        if next_cell.immutable && !is_navigable(next_cell.cell_type) {
+            continue;
+        }
+        current = next;
+    }
+    placed
+}
+
+fn roll_theme(rng: &mut impl Rng, depth: i32) -> RoomTheme {
+    let theme_table = match depth {
+        0..=2 => vec![
+            RoomTheme::Barren,
+            RoomTheme::Mossy,
+            RoomTheme::Mossy,
+            RoomTheme::Meadow,
+            RoomTheme::Meadow,
+            RoomTheme::Meadow,
+            RoomTheme::Marsh,
+        ],
+        3..=7 => vec![
+            RoomTheme::Barren,
+            RoomTheme::Barren,
+            RoomTheme::Mossy,
+            RoomTheme::Mossy,
+            RoomTheme::Meadow,
+            RoomTheme::Marsh,
+            RoomTheme::Marsh,
+        ],
+        _ => vec![
+            RoomTheme::Barren,
+            RoomTheme::Barren,
+            RoomTheme::Barren,
+            RoomTheme::Mossy,
+            RoomTheme::Marsh,
+            RoomTheme::Marsh,
+        ],
+    };
+    *theme_table.choose(rng).unwrap()
+}
+
+fn decorate_room(rng: &mut impl Rng, size: TileSize, room: &mut [Vec<GeneratedCell>], theme: RoomTheme, depth: i32) -> Decorations {
+    let mut decorations = Decorations::default();
+
+    let (moss_patches, thyme_patches, pools, streams) = match theme {
+        RoomTheme::Barren => (0..=1, 0..=0, 0..=1, 0..=0),
+        RoomTheme::Mossy => (2..=4, 0..=1, 0..=1, 0..=0),
+        RoomTheme::Meadow => (0..=1, 2..=4, 0..=0, 0..=1),
+        RoomTheme::Marsh => (1..=2, 0..=1, 1..=3, 1..=1),
+    };
+    // Deeper rooms are wetter and less overgrown
+    let flora_size_range = (4 - depth / 4).max(2) as usize..=(12 - depth / 2).max(4) as usize;
+    let pool_size_range = (3 + depth / 4) as usize..=(6 + depth / 2) as usize;
+
+    // Water first, flora only replaces plain floor so it can't overwrite reported water.
+    for _ in 0..rng.random_range(pools) {
+        let candidates = collect_decoratable_cells(size, room);
+        if let Some(&seed) = candidates.choose(rng) {
+            let target_size = rng.random_range(pool_size_range.clone());
+            decorations.water.append(&mut grow_patch(rng, size, room, seed, target_size, CellType::Water, can_place_water));
+        }
+    }
+    for _ in 0..rng.random_range(streams) {
+        let candidates = collect_decoratable_cells(size, room);
+        if candidates.len() < 2 {
+            break;
+        }
+        let start = *candidates.choose(rng).unwrap();
+        let end = *candidates.choose(rng).unwrap();
+        decorations.water.append(&mut carve_stream(rng, size, room, start, end));
+    }
+    for _ in 0..rng.random_range(moss_patches) {
+        let candidates = collect_decoratable_cells(size, room);
+        if let Some(&seed) = candidates.choose(rng) {
+            let target_size = rng.random_range(flora_size_range.clone());
+            decorations.moss.append(&mut grow_patch(rng, size, room, seed, target_size, CellType::FloorMoss, can_place_flora));
+        }
+    }
+    for _ in 0..rng.random_range(thyme_patches) {
+        let candidates = collect_decoratable_cells(size, room);
+        if let Some(&seed) = candidates.choose(rng) {
+            let target_size = rng.random_range(flora_size_range.clone());
+            decorations.thyme.append(&mut grow_patch(rng, size, room, seed, target_size, CellType::FloorThyme, can_place_flora));
+        }
+    }
+
+    decorations
+}
+
+fn roll_monsters(rng: &mut impl Rng, depth: i32) -> Vec<ActorType> {
 
     let monster_count_range = match depth {
         0..=1 => 3..=5,
@@ -179,6 +418,8 @@ fn roll_monsters(depth: i32) -> Vec<ActorType> {
             ActorType::Toad,
             ActorType::Toad,
             ActorType::Toad,
+            ActorType::Heron,
+            ActorType::Heron,
             ActorType::BlueJelly,
             ActorType::BlueJelly,
             ActorType::ToothyStarling,
@@ -189,6 +430,9 @@ fn roll_monsters(depth: i32) -> Vec<ActorType> {
             ActorType::Toad,
             ActorType::Toad,
             ActorType::Toad,
+            ActorType::Heron,
+            ActorType::StarlightFairy,
+            ActorType::StarlightFairy,
             ActorType::MouseSkirmisher,
             ActorType::MouseSkirmisher,
             ActorType::MouseSkirmisher,
@@ -205,6 +449,9 @@ fn roll_monsters(depth: i32) -> Vec<ActorType> {
             ActorType::Toad,
             ActorType::Toad,
             ActorType::Toad,
+            ActorType::StarlightFairy,
+            ActorType::Mermaid,
+            ActorType::Mermaid,
             ActorType::MouseWarrior,
             ActorType::MouseSkirmisher,
             ActorType::MouseSkirmisher,
@@ -222,6 +469,8 @@ fn roll_monsters(depth: i32) -> Vec<ActorType> {
         _ => vec![
             ActorType::Toad,
             ActorType::Toad,
+            ActorType::Mermaid,
+            ActorType::Mermaid,
             ActorType::MouseWarrior,
             ActorType::MouseWarrior,
             ActorType::MouseWarrior,
@@ -243,14 +492,13 @@ fn roll_monsters(depth: i32) -> Vec<ActorType> {
 
     let mut result = vec![];
     for _ in 0..monster_count {
-        let monster_type = *monster_table.choose(&mut rng).unwrap();
+        let monster_type = *monster_table.choose(rng).unwrap();
         result.push(monster_type);
     }
     result
 }
 
-fn roll_treasure(depth: i32) -> Vec<ItemType> {
-    let mut rng = rand::rng();
+fn roll_treasure(rng: &mut impl Rng, depth: i32) -> Vec<ItemType> {
 
     let item_count_range = match depth {
         0..=1 => 2..=4,
@@ -281,6 +529,9 @@ fn roll_treasure(depth: i32) -> Vec<ItemType> {
         ItemType::Bloodflower,
         ItemType::ElephantFrond,
         ItemType::ArmadilloFlower,
+        ItemType::Acorn,
+        ItemType::Acorn,
+        ItemType::Acorn,
         ItemType::WandOfIce,
         ItemType::WandOfIce,
         ItemType::WandOfIce,
@@ -288,41 +539,16 @@ fn roll_treasure(depth: i32) -> Vec<ItemType> {
 
     let mut result = vec![];
     for _ in 0..item_count {
-        let item_type = *item_table.choose(&mut rng).unwrap();
+        let item_type = *item_table.choose(rng).unwrap();
         result.push(item_type);
     }
     result
 }
 
-pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerationConfig) -> GeneratedRoom {
-    let mut room = create_2d_vec::<GeneratedCell>(config.size);
-    let mut rng = rand::rng();
-
-    let inner_width_range = 1..(config.size.x - 1);
-    let inner_height_range = 1..(config.size.y - 1);
-
-    let player_start = match maybe_player_start {
-        Some(player_start) => vec2(player_start.x as usize, player_start.y as usize),
-        None => vec2(rng.random_range(inner_width_range.clone()), rng.random_range(inner_height_range.clone())),
-    };
-
-    // Initialize randomly
-    for x in 0..config.size.x { for y in 0..config.size.y {
-        if x == 0 || y == 0 || x == config.size.x - 1 || y == config.size.y - 1 {
-            room[x][y].cell_type = CellType::DefaultWall;
-            room[x][y].immutable = true;
-        } else if x == player_start.x && y == player_start.y {
-            room[x][y].cell_type = CellType::DefaultFloor;
-            room[x][y].immutable = true;
-        } else {
-            room[x][y].cell_type = if rng.random::<f32>() < 0.7 { CellType::DefaultFloor } else { CellType::DefaultWall };
-        }
-    }}
-
-    // Smooth with cellular automata
+fn smooth_with_cellular_automata(rng: &mut impl Rng, size: TileSize, room: &mut Vec<Vec<GeneratedCell>>) {
     for _i in 0..2 {
         let mut next_room = room.clone();
-        for x in inner_width_range.clone() { for y in inner_height_range.clone() {
+        for x in 1..(size.x - 1) { for y in 1..(size.y - 1) {
             if room[x][y].immutable {
                 next_room[x][y] = room[x][y].clone();
                 continue;
@@ -352,7 +578,291 @@ pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerati
             }
             next_room[x][y].cell_type = next_cell_type;
         }}
-        room = next_room;
+        *room = next_room;
+    }
+}
+
+fn generate_caves(rng: &mut impl Rng, size: TileSize, room: &mut Vec<Vec<GeneratedCell>>) {
+
+    // Initialize randomly
+    #[allow(clippy::needless_range_loop)]
+    for x in 0..size.x { for y in 0..size.y {
+        if !room[x][y].immutable {
+            room[x][y].cell_type = if rng.random::<f32>() < 0.7 { CellType::DefaultFloor } else { CellType::DefaultWall };
+        }
+    }}
+
+    smooth_with_cellular_automata(rng, size, room);
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TileRect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl TileRect {
+    fn center(&self) -> TilePoint {
+        vec2((self.x + self.width / 2) as i32, (self.y + self.height / 2) as i32)
+    }
+}
+
+fn fill_walls(size: TileSize, room: &mut [Vec<GeneratedCell>]) {
+    #[allow(clippy::needless_range_loop)]
+    for x in 0..size.x { for y in 0..size.y {
+        if !room[x][y].immutable {
+            room[x][y].cell_type = CellType::DefaultWall;
+        }
+    }}
+}
+
+fn carve_rect(room: &mut [Vec<GeneratedCell>], rect: TileRect) {
+    #[allow(clippy::needless_range_loop)]
+    for x in rect.x..(rect.x + rect.width) { for y in rect.y..(rect.y + rect.height) {
+        if !room[x][y].immutable {
+            room[x][y].cell_type = CellType::DefaultFloor;
+        }
+    }}
+}
+
+// L-shaped corridor, horizontal leg first
+fn carve_corridor(room: &mut [Vec<GeneratedCell>], start: TilePoint, end: TilePoint) {
+    let mut current = start;
+    loop {
+        let cell = &mut room[current.x as usize][current.y as usize];
+        if !cell.immutable {
+            cell.cell_type = CellType::DefaultFloor;
+        }
+        if current == end {
+            break;
+        }
+        if current.x != end.x {
+            current.x += (end.x - current.x).signum();
+        } else {
+            current.y += (end.y - current.y).signum();
+        }
+    }
+}
+
+const BSP_MIN_LEAF_SIZE: usize = 5;
+
+fn split_bsp_leaves(rng: &mut impl Rng, size: TileSize) -> Vec<TileRect> {
+    let mut leaves = vec![];
+    let mut pending = vec![ TileRect { x: 1, y: 1, width: size.x - 2, height: size.y - 2 } ];
+    while let Some(rect) = pending.pop() {
+        let can_split_x = rect.width >= BSP_MIN_LEAF_SIZE * 2;
+        let can_split_y = rect.height >= BSP_MIN_LEAF_SIZE * 2;
+        let split_x = if can_split_x && can_split_y {
+            // Prefer splitting along the longer axis
+            rect.width >= rect.height
+        } else if can_split_x || can_split_y {
+            can_split_x
+        } else {
+            leaves.push(rect);
+            continue;
+        };
+        if split_x {
+            let split = rng.random_range(BSP_MIN_LEAF_SIZE..=(rect.width - BSP_MIN_LEAF_SIZE));
+            pending.push(TileRect { x: rect.x, y: rect.y, width: split, height: rect.height });
+            pending.push(TileRect { x: rect.x + split, y: rect.y, width: rect.width - split, height: rect.height });
+        } else {
+            let split = rng.random_range(BSP_MIN_LEAF_SIZE..=(rect.height - BSP_MIN_LEAF_SIZE));
+            pending.push(TileRect { x: rect.x, y: rect.y, width: rect.width, height: split });
+            pending.push(TileRect { x: rect.x, y: rect.y + split, width: rect.width, height: rect.height - split });
+        }
+    }
+    leaves
+}
+
+fn generate_rooms_and_corridors(rng: &mut impl Rng, size: TileSize, room: &mut [Vec<GeneratedCell>]) {
+    fill_walls(size, room);
+
+    let mut rooms = vec![];
+    for leaf in split_bsp_leaves(rng, size).into_iter() {
+        // Only happens when the whole room is too small to split, so there are no neighbors to wall off
+        if leaf.width < 3 || leaf.height < 3 {
+            carve_rect(room, leaf);
+            rooms.push(leaf);
+            continue;
+        }
+        // Leave at least one wall between neighboring leaves
+        let width = rng.random_range(2.max(leaf.width / 2)..leaf.width);
+        let height = rng.random_range(2.max(leaf.height / 2)..leaf.height);
+        let x = leaf.x + rng.random_range(0..(leaf.width - width));
+        let y = leaf.y + rng.random_range(0..(leaf.height - height));
+        let rect = TileRect { x, y, width, height };
+        carve_rect(room, rect);
+        rooms.push(rect);
+    }
+
+    // Chain rooms in order so each is connected to the previous one
+    for pair in rooms.windows(2) {
+        carve_corridor(room, pair[0].center(), pair[1].center());
+    }
+}
+
+fn generate_tunnels(rng: &mut impl Rng, size: TileSize, room: &mut [Vec<GeneratedCell>], player_start: TilePoint) {
+    fill_walls(size, room);
+
+    let inner_area = (size.x - 2) * (size.y - 2);
+    let target_floor_count = inner_area * 2 / 5;
+    let max_steps = inner_area * 20;
+    let mut floor_count = 0;
+    let mut current = player_start;
+    for _ in 0..max_steps {
+        if floor_count >= target_floor_count {
+            break;
+        }
+        let cell = &mut room[current.x as usize][current.y as usize];
+        if cell.cell_type == CellType::DefaultWall && !cell.immutable {
+            cell.cell_type = CellType::DefaultFloor;
+            floor_count += 1;
+        }
+        let delta = *NEIGHBORS.choose(rng).unwrap();
+        let next = vec2(current.x + delta.0, current.y + delta.1);
+        if next.x > 0 && next.y > 0 && (next.x as usize) < size.x - 1 && (next.y as usize) < size.y - 1 {
+            current = next;
+        }
+    }
+}
+
+fn generate_mixed(rng: &mut impl Rng, size: TileSize, room: &mut Vec<Vec<GeneratedCell>>) {
+    generate_caves(rng, size, room);
+
+    // Stamp walled structures with doorways over the caves
+    let structure_count = rng.random_range(1..=2);
+    for _ in 0..structure_count {
+        if size.x < 9 || size.y < 7 {
+            break;
+        }
+        let width = rng.random_range(5..=(size.x - 2).min(9));
+        let height = rng.random_range(5..=(size.y - 2).min(7));
+        let x = rng.random_range(1..=(size.x - 1 - width));
+        let y = rng.random_range(1..=(size.y - 1 - height));
+        #[allow(clippy::needless_range_loop)]
+        for sx in x..(x + width) { for sy in y..(y + height) {
+            if room[sx][sy].immutable {
+                continue;
+            }
+            let is_outline = sx == x || sy == y || sx == x + width - 1 || sy == y + height - 1;
+            room[sx][sy].cell_type = if is_outline { CellType::DefaultWall } else { CellType::DefaultFloor };
+        }}
+        let doorways = [
+            vec2(x + width / 2, y),
+            vec2(x + width / 2, y + height - 1),
+            vec2(x, y + height / 2),
+            vec2(x + width - 1, y + height / 2),
+        ];
+        for doorway in doorways.choose_multiple(rng, 2) {
+            if !room[doorway.x][doorway.y].immutable {
+                room[doorway.x][doorway.y].cell_type = CellType::DefaultFloor;
+            }
+        }
+    }
+}
+
+// Stamps the prefab somewhere it fits, keeping a gap from the border and the player start so its openings
+// always lead somewhere the generator can connect. Returns the top-left corner if it was placed.
+fn try_stamp_prefab(rng: &mut impl Rng, size: TileSize, room: &mut [Vec<GeneratedCell>], prefab: &Prefab, player_start: TilePoint) -> Option<TilePoint> {
+    if size.x < prefab.size.x + 4 || size.y < prefab.size.y + 4 {
+        return None;
+    }
+    for _attempt in 0..10 {
+        let x = rng.random_range(2..=(size.x - 2 - prefab.size.x));
+        let y = rng.random_range(2..=(size.y - 2 - prefab.size.y));
+        let near_player_start = player_start.x >= x as i32 - 1 && player_start.x <= (x + prefab.size.x) as i32
+            && player_start.y >= y as i32 - 1 && player_start.y <= (y + prefab.size.y) as i32;
+        if near_player_start {
+            continue;
+        }
+        for px in 0..prefab.size.x { for py in 0..prefab.size.y {
+            if let Some(cell) = &prefab.cells[px][py] {
+                room[x + px][y + py] = cell.clone();
+            }
+        }}
+        return Some(vec2(x as i32, y as i32));
+    }
+    None
+}
+
+pub fn roll_room_size(rng: &mut impl Rng, depth: i32) -> TileSize {
+    let (width_range, height_range) = match depth {
+        0..=2 => (19..=27, 11..=15),
+        3..=7 => (23..=40, 13..=20),
+        _ => (30..=60, 16..=30),
+    };
+    vec2(rng.random_range(width_range), rng.random_range(height_range))
+}
+
+pub fn roll_strategy(rng: &mut impl Rng, depth: i32) -> GenerationStrategy {
+    let strategy_table = match depth {
+        0..=2 => vec![
+            GenerationStrategy::Caves,
+            GenerationStrategy::Caves,
+            GenerationStrategy::Caves,
+            GenerationStrategy::Tunnels,
+        ],
+        3..=6 => vec![
+            GenerationStrategy::Caves,
+            GenerationStrategy::Caves,
+            GenerationStrategy::Tunnels,
+            GenerationStrategy::Mixed,
+        ],
+        7..=11 => vec![
+            GenerationStrategy::Caves,
+            GenerationStrategy::Mixed,
+            GenerationStrategy::Mixed,
+            GenerationStrategy::RoomsAndCorridors,
+        ],
+        _ => vec![
+            GenerationStrategy::Mixed,
+            GenerationStrategy::RoomsAndCorridors,
+            GenerationStrategy::RoomsAndCorridors,
+        ],
+    };
+    *strategy_table.choose(rng).unwrap()
+}
+
+fn generate_room_with_rng(rng: &mut impl Rng, maybe_player_start: Option<TilePoint>, config: &RoomGenerationConfig) -> GeneratedRoom {
+    assert!(config.size.x >= 3 && config.size.y >= 3, "room {:?} has no space inside its border", config.size);
+    let mut room = create_2d_vec::<GeneratedCell>(config.size);
+
+    let inner_width_range = 1..(config.size.x - 1);
+    let inner_height_range = 1..(config.size.y - 1);
+
+    let player_start = match maybe_player_start {
+        Some(player_start) => vec2(player_start.x as usize, player_start.y as usize),
+        None => vec2(rng.random_range(inner_width_range.clone()), rng.random_range(inner_height_range.clone())),
+    };
+
+    // Fix the border and player start so no strategy changes them
+    #[allow(clippy::needless_range_loop)]
+    for x in 0..config.size.x { for y in 0..config.size.y {
+        if x == 0 || y == 0 || x == config.size.x - 1 || y == config.size.y - 1 {
+            room[x][y].cell_type = CellType::DefaultWall;
+            room[x][y].immutable = true;
+        } else if x == player_start.x && y == player_start.y {
+            room[x][y].cell_type = CellType::DefaultFloor;
+            room[x][y].immutable = true;
+        }
+    }}
+
+    let player_start_i32 = vec2(player_start.x as i32, player_start.y as i32);
+
+    // Stamp a prefab first so the layout strategies build around its immutable cells
+    let eligible_prefabs: Vec<&Prefab> = prefab::get_prefabs().iter().filter(|p| p.min_depth <= config.depth).collect();
+    if !eligible_prefabs.is_empty() && rng.random::<f32>() < 0.35 {
+        let chosen_prefab = *eligible_prefabs.choose(rng).unwrap();
+        try_stamp_prefab(rng, config.size, &mut room, chosen_prefab, player_start_i32);
+    }
+
+    match config.strategy {
+        GenerationStrategy::Caves => generate_caves(rng, config.size, &mut room),
+        GenerationStrategy::RoomsAndCorridors => generate_rooms_and_corridors(rng, config.size, &mut room),
+        GenerationStrategy::Tunnels => generate_tunnels(rng, config.size, &mut room, player_start_i32),
+        GenerationStrategy::Mixed => generate_mixed(rng, config.size, &mut room),
     }
 
     // Erase small islands
@@ -372,28 +882,49 @@ pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerati
     // Connect large islands
     {
         let navigable_areas = partition_navigable_areas(config.size, &room);
-        let first_area = navigable_areas[0].iter().collect::<Vec<&TilePoint>>();
-        for i in 1..navigable_areas.len() {
-            let area = navigable_areas[i].iter().collect::<Vec<&TilePoint>>();
-            let start = **first_area.choose(&mut rng).unwrap();
-            let end = **area.choose(&mut rng).unwrap();
-            connect_with_drunkards_walk(&mut room, start, end);
+        if let Some((first_area, other_areas)) = navigable_areas.split_first() {
+            let first_area = find_area_frontier(&room, first_area);
+            for other_area in other_areas.iter() {
+                let area = find_area_frontier(&room, other_area);
+                let start = *first_area.choose(rng).unwrap();
+                let end = *area.choose(rng).unwrap();
+                connect_with_drunkards_walk(rng, &mut room, start, end);
+            }
         }
     }
 
-    // Place exit(s)
+    // Place exit(s). A room with no candidates is left without exits for validation to reject.
     let mut exits = vec![];
-    {
-        let exit_candidates = find_edge_walls(config.size, &room);
-        let exit = exit_candidates.choose(&mut rng).unwrap();
-        exits.push(*exit);
+    if let Some(&exit) = find_edge_walls(config.size, &room).choose(rng) {
+        exits.push(exit);
         room[exit.x as usize][exit.y as usize].cell_type = CellType::RoomExit;
     }
 
-    let player_start_i32 = vec2(player_start.x as i32, player_start.y as i32);
+    let theme = roll_theme(rng, config.depth);
+    let decorations = decorate_room(rng, config.size, &mut room, theme, config.depth);
+
     let mut open_cells: Vec<TilePoint> = collect_open_cells(config.size, &room, player_start_i32);
 
-    let monster_types = roll_monsters(config.depth);
+    // Plants grow on moss and thyme patches
+    open_cells.retain(|&pos| {
+        let cell = &mut room[pos.x as usize][pos.y as usize];
+        let plant_type = match cell.cell_type {
+            CellType::FloorMoss => ActorType::BloodflowerBush,
+            CellType::FloorThyme => ActorType::BerryShrub,
+            _ => return true,
+        };
+        if rng.random::<f32>() < 0.08 {
+            cell.monster = Some(plant_type);
+            false
+        } else {
+            true
+        }
+    });
+
+    // Population tables are balanced for the original 19x11 room, roll them again for every multiple of that
+    let population_rolls = ((config.size.x * config.size.y) as f32 / (19.0 * 11.0)).round().max(1.0) as usize;
+
+    let monster_types: Vec<ActorType> = (0..population_rolls).flat_map(|_| roll_monsters(rng, config.depth)).collect();
     for monster_type in monster_types.into_iter() {
         if open_cells.is_empty() {
             break;
@@ -403,7 +934,7 @@ pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerati
         room[pos.x as usize][pos.y as usize].monster = Some(monster_type);
     }
 
-    let treasure = roll_treasure(config.depth);
+    let treasure: Vec<ItemType> = (0..population_rolls).flat_map(|_| roll_treasure(rng, config.depth)).collect();
     for item_type in treasure.into_iter() {
         if open_cells.is_empty() {
             break;
@@ -418,7 +949,7 @@ pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerati
         2..=4 => 4,
         _ => 12,
     };
-    let thistle_count = rng.random_range(0..=thistle_max);
+    let thistle_count = rng.random_range(0..=thistle_max) * population_rolls;
     for _ in 0..thistle_count {
         if open_cells.is_empty() {
             break;
@@ -438,19 +969,125 @@ pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerati
         cells: room,
         exits,
         player_start: player_start_i32,
+        theme,
+        decorations,
+    }
+}
+
+// Regenerating is cheap, so give up only if something is badly wrong with the config
+const MAX_GENERATION_ATTEMPTS: usize = 20;
+
+pub fn generate_room(maybe_player_start: Option<TilePoint>, config: RoomGenerationConfig) -> GeneratedRoom {
+    let mut rng = rand::rng();
+    generate_valid_room(MAX_GENERATION_ATTEMPTS, maybe_player_start, &config, |player_start, config| {
+        generate_room_with_rng(&mut rng, player_start, config)
+    })
+}
+
+// Generator bugs are caught by the seeded property test. If one still slips through, players get a plain room
+// they can leave instead of one they're stuck in.
+fn generate_valid_room(
+    max_attempts: usize,
+    maybe_player_start: Option<TilePoint>,
+    config: &RoomGenerationConfig,
+    mut generate: impl FnMut(Option<TilePoint>, &RoomGenerationConfig) -> GeneratedRoom,
+) -> GeneratedRoom {
+    for _ in 0..max_attempts {
+        let room = generate(maybe_player_start, config);
+        if validate_room(&room).is_empty() {
+            return room;
+        }
+    }
+    generate_fallback_room(maybe_player_start, config)
+}
+
+// An empty walled room with one exit, valid for any size the generator accepts
+fn generate_fallback_room(maybe_player_start: Option<TilePoint>, config: &RoomGenerationConfig) -> GeneratedRoom {
+    let size = config.size;
+    let mut cells = create_2d_vec::<GeneratedCell>(size);
+    #[allow(clippy::needless_range_loop)]
+    for x in 0..size.x { for y in 0..size.y {
+        cells[x][y].cell_type = if x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1 {
+            CellType::DefaultWall
+        } else {
+            CellType::DefaultFloor
+        };
+    }}
+    let exit = vec2(size.x as i32 - 1, size.y as i32 / 2);
+    cells[exit.x as usize][exit.y as usize].cell_type = CellType::RoomExit;
+    GeneratedRoom {
+        cells,
+        exits: vec![ exit ],
+        player_start: maybe_player_start.unwrap_or(vec2(size.x as i32 / 2, size.y as i32 / 2)),
+        theme: RoomTheme::Barren,
+        decorations: Decorations::default(),
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RoomViolation {
+    NoExits,
+    ExitNotMarked(TilePoint),
+    ExitUnreachable(TilePoint),
+    PlayerStartNotNavigable,
+    PlayerStartOccupied,
+    BorderNotWalled(TilePoint),
+    EntityNotOnFloor(TilePoint),
+}
+
+pub fn validate_room(room: &GeneratedRoom) -> Vec<RoomViolation> {
+    let mut violations = vec![];
+    let size = vec2(room.cells.len(), room.cells[0].len());
+
+    let start_cell = &room.cells[room.player_start.x as usize][room.player_start.y as usize];
+    if !is_navigable(start_cell.cell_type) {
+        violations.push(RoomViolation::PlayerStartNotNavigable);
+    }
+    if start_cell.monster.is_some() || start_cell.item.is_some() || start_cell.misc_entity.is_some() {
+        violations.push(RoomViolation::PlayerStartOccupied);
+    }
+
+    if room.exits.is_empty() {
+        violations.push(RoomViolation::NoExits);
+    }
+    let reachable = floodfill_navigable_area(&room.cells, room.player_start);
+    for &exit in room.exits.iter() {
+        if room.cells[exit.x as usize][exit.y as usize].cell_type != CellType::RoomExit {
+            violations.push(RoomViolation::ExitNotMarked(exit));
+        } else if !reachable.contains(&exit) {
+            violations.push(RoomViolation::ExitUnreachable(exit));
+        }
     }
+
+    for x in 0..size.x { for y in 0..size.y {
+        let p = vec2(x as i32, y as i32);
+        let cell = &room.cells[x][y];
+        let is_border = x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1;
+        if is_border && !matches!(cell.cell_type, CellType::DefaultWall | CellType::RoomExit) {
+            violations.push(RoomViolation::BorderNotWalled(p));
+        }
+        let has_entity = cell.monster.is_some() || cell.item.is_some() || cell.misc_entity.is_some();
+        if has_entity && !is_open(cell.cell_type) {
+            violations.push(RoomViolation::EntityNotOnFloor(p));
+        }
+    }}
+
+    violations
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
     use super::*;
 
     #[test]
     fn test_partition_navigable_areas() {
         let size = vec2(5, 5);
         let mut room = create_2d_vec::<GeneratedCell>(size);
-        for x in 0..size.x { for y in 0..size.y {
-            room[x][y].cell_type = CellType::DefaultWall;
+        for column in room.iter_mut() { for cell in column.iter_mut() {
+            cell.cell_type = CellType::DefaultWall;
         }}
         room[1][1].cell_type = CellType::DefaultFloor;
         room[3][1].cell_type = CellType::DefaultFloor;
@@ -464,4 +1101,269 @@ mod tests {
         assert!(partition_result[1].contains(&vec2(3, 1)));
         assert!(partition_result[1].contains(&vec2(3, 2)));
     }
+
+    fn create_blank_room(size: TileSize) -> Vec<Vec<GeneratedCell>> {
+        let mut room = create_2d_vec::<GeneratedCell>(size);
+        #[allow(clippy::needless_range_loop)]
+        for x in 0..size.x { for y in 0..size.y {
+            if x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1 {
+                room[x][y].cell_type = CellType::DefaultWall;
+                room[x][y].immutable = true;
+            } else {
+                room[x][y].cell_type = CellType::DefaultFloor;
+            }
+        }}
+        room
+    }
+
+    #[test]
+    fn test_decorate_room_reports_placements() {
+        let mut rng = rand::rng();
+        let size = vec2(20, 12);
+        for theme in [RoomTheme::Barren, RoomTheme::Mossy, RoomTheme::Meadow, RoomTheme::Marsh] {
+            let mut room = create_blank_room(size);
+            let decorations = decorate_room(&mut rng, size, &mut room, theme, 4);
+            for p in decorations.moss.iter() {
+                assert_eq!(CellType::FloorMoss, room[p.x as usize][p.y as usize].cell_type);
+            }
+            for p in decorations.thyme.iter() {
+                assert_eq!(CellType::FloorThyme, room[p.x as usize][p.y as usize].cell_type);
+            }
+            for p in decorations.water.iter() {
+                assert_eq!(CellType::Water, room[p.x as usize][p.y as usize].cell_type);
+            }
+            match theme {
+                RoomTheme::Barren => assert!(decorations.thyme.is_empty()),
+                RoomTheme::Mossy => assert!(!decorations.moss.is_empty()),
+                RoomTheme::Meadow => assert!(!decorations.thyme.is_empty()),
+                RoomTheme::Marsh => assert!(!decorations.water.is_empty()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decorate_room_keeps_immutable_cells() {
+        let mut rng = rand::rng();
+        let size = vec2(20, 12);
+        let mut room = create_blank_room(size);
+        room[5][5].immutable = true;
+        decorate_room(&mut rng, size, &mut room, RoomTheme::Marsh, 10);
+        assert_eq!(CellType::DefaultFloor, room[5][5].cell_type);
+        for column in room.iter() {
+            assert_eq!(CellType::DefaultWall, column[0].cell_type);
+            assert_eq!(CellType::DefaultWall, column[size.y - 1].cell_type);
+        }
+    }
+
+    #[test]
+    fn test_generated_exit_reachable_after_decoration() {
+        for depth in 0..16 {
+            let room = generate_room(None, RoomGenerationConfig { depth, size: vec2(19, 11), strategy: GenerationStrategy::Caves });
+            assert_eq!(Vec::<RoomViolation>::new(), validate_room(&room));
+        }
+    }
+
+    #[test]
+    fn test_split_bsp_leaves() {
+        let mut rng = rand::rng();
+        let size = vec2(30, 20);
+        let leaves = split_bsp_leaves(&mut rng, size);
+        assert!(leaves.len() > 1);
+        let mut covered = 0;
+        for leaf in leaves.iter() {
+            assert!(leaf.width >= BSP_MIN_LEAF_SIZE && leaf.height >= BSP_MIN_LEAF_SIZE);
+            covered += leaf.width * leaf.height;
+        }
+        // Leaves tile the inner area exactly
+        assert_eq!((size.x - 2) * (size.y - 2), covered);
+    }
+
+    #[test]
+    fn test_all_strategies_reach_exit() {
+        let strategies = [
+            GenerationStrategy::Caves,
+            GenerationStrategy::RoomsAndCorridors,
+            GenerationStrategy::Tunnels,
+            GenerationStrategy::Mixed,
+        ];
+        for strategy in strategies {
+            for depth in [0, 5, 12] {
+                let player_start = vec2(3, 3);
+                let room = generate_room(Some(player_start), RoomGenerationConfig { depth, size: vec2(19, 11), strategy });
+                assert_eq!(player_start, room.player_start);
+                assert_eq!(Vec::<RoomViolation>::new(), validate_room(&room), "{:?}", strategy);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stamp_prefab() {
+        let mut rng = rand::rng();
+        let prefab = prefab::parse_prefab("name: test\nlegend: z = monster dusty skeleton\n---\n###\n#z#\n#.#\n").unwrap();
+        let size = vec2(19, 11);
+        let player_start = vec2(1, 1);
+        let mut room = create_blank_room(size);
+        let corner = try_stamp_prefab(&mut rng, size, &mut room, &prefab, player_start).unwrap();
+        let skeleton_cell = &room[corner.x as usize + 1][corner.y as usize + 1];
+        assert!(skeleton_cell.immutable);
+        assert_eq!(Some(ActorType::DustySkeleton), skeleton_cell.monster);
+        assert!(room[corner.x as usize][corner.y as usize].immutable);
+        assert!(!room[player_start.x as usize][player_start.y as usize].immutable);
+
+        // Doesn't fit
+        let mut small_room = create_blank_room(vec2(6, 6));
+        assert!(try_stamp_prefab(&mut rng, vec2(6, 6), &mut small_room, &prefab, player_start).is_none());
+    }
+
+    #[test]
+    fn test_prefab_rooms_reach_exit() {
+        let mut rng = rand::rng();
+        let prefab = prefab::parse_prefab("name: test\n---\n#####\n#...#\n#...#\n##.##\n").unwrap();
+        for strategy in [GenerationStrategy::Caves, GenerationStrategy::RoomsAndCorridors, GenerationStrategy::Tunnels] {
+            for _ in 0..10 {
+                let size = vec2(19, 11);
+                let player_start = vec2(1, 1);
+                let mut room = create_blank_room(size);
+                room[1][1].immutable = true;
+                let corner = try_stamp_prefab(&mut rng, size, &mut room, &prefab, player_start).unwrap();
+                match strategy {
+                    GenerationStrategy::Caves => generate_caves(&mut rng, size, &mut room),
+                    GenerationStrategy::RoomsAndCorridors => generate_rooms_and_corridors(&mut rng, size, &mut room),
+                    _ => generate_tunnels(&mut rng, size, &mut room, player_start),
+                }
+                let navigable_areas = partition_navigable_areas(size, &room);
+                let first_area = find_area_frontier(&room, &navigable_areas[0]);
+                for area in navigable_areas.iter().skip(1) {
+                    let end = find_area_frontier(&room, area)[0];
+                    connect_with_drunkards_walk(&mut rng, &mut room, first_area[0], end);
+                }
+                let reachable = floodfill_navigable_area(&room, player_start);
+                assert!(reachable.contains(&vec2(corner.x + 2, corner.y + 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_room() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let config = RoomGenerationConfig { depth: 3, size: vec2(19, 11), strategy: GenerationStrategy::Caves };
+        let mut room = generate_room_with_rng(&mut rng, Some(vec2(3, 3)), &config);
+        assert!(validate_room(&room).is_empty());
+
+        room.cells[3][3].monster = Some(ActorType::Toad);
+        room.cells[0][0].cell_type = CellType::DefaultFloor;
+        let exit = room.exits[0];
+        room.cells[exit.x as usize][exit.y as usize].cell_type = CellType::DefaultWall;
+        let violations = validate_room(&room);
+        assert!(violations.contains(&RoomViolation::PlayerStartOccupied));
+        assert!(violations.contains(&RoomViolation::BorderNotWalled(vec2(0, 0))));
+        assert!(violations.contains(&RoomViolation::ExitNotMarked(exit)));
+
+        room.exits.clear();
+        assert!(validate_room(&room).contains(&RoomViolation::NoExits));
+    }
+
+    #[test]
+    fn test_validate_room_unreachable_exit() {
+        let size = vec2(7, 5);
+        let mut cells = create_blank_room(size);
+        #[allow(clippy::needless_range_loop)]
+        for y in 1..4 {
+            cells[3][y].cell_type = CellType::DefaultWall;
+        }
+        cells[6][2].cell_type = CellType::RoomExit;
+        let room = GeneratedRoom {
+            cells,
+            exits: vec![vec2(6, 2)],
+            player_start: vec2(1, 2),
+            theme: RoomTheme::Barren,
+            decorations: Decorations::default(),
+        };
+        assert_eq!(vec![RoomViolation::ExitUnreachable(vec2(6, 2))], validate_room(&room));
+    }
+
+    #[test]
+    fn test_generate_large_rooms() {
+        let mut rng = StdRng::seed_from_u64(200);
+        for strategy in [GenerationStrategy::Caves, GenerationStrategy::RoomsAndCorridors, GenerationStrategy::Tunnels, GenerationStrategy::Mixed] {
+            let config = RoomGenerationConfig { depth: 10, size: vec2(200, 100), strategy };
+            let room = generate_room_with_rng(&mut rng, None, &config);
+            assert_eq!(200, room.cells.len());
+            assert_eq!(100, room.cells[0].len());
+            assert!(validate_room(&room).is_empty(), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_roll_room_size() {
+        let mut rng = rand::rng();
+        for depth in 0..16 {
+            let size = roll_room_size(&mut rng, depth);
+            assert!(size.x >= 19 && size.y >= 11);
+        }
+    }
+
+    // Property test over seeded rooms, so any failure can be replayed from the printed seed
+    #[test]
+    fn test_generated_rooms_are_valid() {
+        let sizes = [vec2(3, 3), vec2(4, 7), vec2(8, 5), vec2(12, 12), vec2(19, 11), vec2(40, 20)];
+        let strategies = [
+            GenerationStrategy::Caves,
+            GenerationStrategy::RoomsAndCorridors,
+            GenerationStrategy::Tunnels,
+            GenerationStrategy::Mixed,
+        ];
+        let mut seed = 0;
+        for size in sizes {
+            for strategy in strategies {
+                for depth in 0..16 {
+                    for _ in 0..6 {
+                        seed += 1;
+                        let mut rng = StdRng::seed_from_u64(seed);
+                        let config = RoomGenerationConfig { depth, size, strategy };
+                        let room = generate_room_with_rng(&mut rng, None, &config);
+                        let violations = validate_room(&room);
+                        assert!(violations.is_empty(), "seed {} {:?}: {:?}", seed, config, violations);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_valid_room_retries() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let config = RoomGenerationConfig { depth: 2, size: vec2(19, 11), strategy: GenerationStrategy::Caves };
+        let mut attempts = 0;
+        let room = generate_valid_room(5, None, &config, |player_start, config| {
+            attempts += 1;
+            let mut room = generate_room_with_rng(&mut rng, player_start, config);
+            if attempts < 3 {
+                room.exits.clear();
+            }
+            room
+        });
+        assert_eq!(3, attempts);
+        assert!(validate_room(&room).is_empty());
+    }
+
+    #[test]
+    fn test_generate_valid_room_falls_back() {
+        let mut rng = StdRng::seed_from_u64(8);
+        for (size, player_start) in [(vec2(3, 3), None), (vec2(19, 11), Some(vec2(4, 7))), (vec2(40, 20), None)] {
+            let config = RoomGenerationConfig { depth: 2, size, strategy: GenerationStrategy::Caves };
+            let mut attempts = 0;
+            let room = generate_valid_room(4, player_start, &config, |player_start, config| {
+                attempts += 1;
+                let mut room = generate_room_with_rng(&mut rng, player_start, config);
+                room.exits.clear();
+                room
+            });
+            assert_eq!(4, attempts);
+            assert_eq!(Vec::<RoomViolation>::new(), validate_room(&room));
+            if let Some(player_start) = player_start {
+                assert_eq!(player_start, room.player_start);
+            }
+        }
+    }
 }
@@ -1,31 +1,43 @@
 mod data;
 mod game_model;
 mod generate;
+mod prefab;
 mod strings;
 mod ui_common;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::thread;
+use std::time::Duration;
 
-use color_eyre::Result;
+use color_eyre::{Result, eyre::eyre};
 use cgmath::vec2;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
 use ratatui::{
     buffer::Buffer,
-    layout::Rect,
+    layout::{Constraint, Layout, Rect},
     style::{Color, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Paragraph, Widget},
+    widgets::{Block, Paragraph, Widget, Wrap},
     widgets::block::Padding,
     DefaultTerminal, Frame,
 };
 
-use data::{ActorType, CellType, ItemType, MiscEntityType, GameEvent, STEEL_THISTLE_CYCLE_MAX, TilePoint, TileDelta};
-use game_model::{Command, GameInstance};
+use data::{ACTION_COST, ALL_EQUIP_SLOTS, ALL_ITEM_CATEGORIES, ActorType, CellType, ItemCategory, ItemType, MiscEntityType, GameEvent, ProjectileLanding, STEEL_THISTLE_CYCLE_MAX, TilePoint, TileDelta, HungerState, VisualEffect, get_hunger_state, get_next_level_data, get_projectile_data};
+use game_model::{Command, GameInstance, MiscEntity, StatBonus, get_item_stat_bonus};
 use strings::NamedType;
-use ui_common::ItemMenu;
+use ui_common::{ItemAction, ItemMenu, get_item_actions};
 
-const MAIN_AREA_HEIGHT: u16 = 16;
+const SIDE_HUD_WIDTH: u16 = 16;
+// Border plus up to 7 lines of events
+const EVENT_AREA_HEIGHT: u16 = 9;
+const REMINDER_HEIGHT: u16 = 2;
+// Enough for the longest reminder line and a few rows of map
+const MIN_TERMINAL_WIDTH: u16 = 64;
+const MIN_TERMINAL_HEIGHT: u16 = 20;
+// Per tile a projectile moves
+const PROJECTILE_FRAME_DURATION: Duration = Duration::from_millis(25);
+const FLASH_FRAME_DURATION: Duration = Duration::from_millis(80);
 
 struct CellDisplay {
     c: char,
@@ -42,6 +54,62 @@ fn display_for_cell_type(cell_type: CellType) -> CellDisplay {
         CellType::DefaultWall => CellDisplay { c: '#', fg_color: Color::Black, bg_color: Color::White },
         CellType::RoomExit => CellDisplay { c: 'o', fg_color: Color::White, bg_color: Color::LightBlue },
         CellType::Water => CellDisplay { c: '~', fg_color: Color::Cyan, bg_color: Color::Black },
+        CellType::Ice => CellDisplay { c: '=', fg_color: Color::LightCyan, bg_color: Color::Black },
+    }
+}
+
+fn get_char_for_actor_type(actor_type: ActorType) -> Span<'static> {
+    match actor_type {
+        ActorType::Player => "@".light_yellow().on_black(),
+        ActorType::Toad => "t".light_green().on_black(),
+        ActorType::MouseWarrior => "m".light_cyan().on_black(),
+        ActorType::MouseSkirmisher => "m".magenta().on_black(),
+        ActorType::ToothyStarling => "s".cyan().on_black(),
+        ActorType::DustySkeleton => "z".white().on_black(),
+        ActorType::BlueJelly => "j".light_blue().on_black(),
+        ActorType::BloodflowerBush => "b".light_red().on_black(),
+        ActorType::BerryShrub => "b".blue().on_black(),
+        ActorType::Heron => "h".white().on_black(),
+        ActorType::StarlightFairy => "y".light_yellow().on_black(),
+        ActorType::Mermaid => "m".yellow().on_black(),
+    }
+}
+
+fn get_char_for_item_type(item_type: ItemType) -> Span<'static> {
+    match item_type {
+        ItemType::LumpOfBlackstone => "*".gray().on_black(),
+        ItemType::Javelin => "|".yellow().on_black(),
+        ItemType::BlackstoneSpear => "|".gray().on_black(),
+        ItemType::CarmineSword => "\\".red().on_black(),
+        ItemType::MoonlightKnife => "-".white().on_black(),
+        ItemType::BoneLamellar => "[".white().on_black(),
+        ItemType::FeatheredCavalier => "^".yellow().on_black(),
+        ItemType::CarmineHelm => "^".red().on_black(),
+        ItemType::CarmineChainmail => "[".red().on_black(),
+        ItemType::Bloodflower => "%".light_red().on_black(),
+        ItemType::ElephantFrond => "%".light_green().on_black(),
+        ItemType::ArmadilloFlower => "%".light_yellow().on_black(),
+        ItemType::Darkberry => ":".blue().on_black(),
+        ItemType::Acorn => "%".yellow().on_black(),
+        ItemType::Carcass => "%".red().on_black(),
+        ItemType::WandOfIce => "/".light_cyan().on_black(),
+    }
+}
+
+fn get_char_for_misc_entity(entity: &MiscEntity) -> Span<'static> {
+    const STEEL_THISTLE_CYCLE_MAX_MINUS_1: i32 = STEEL_THISTLE_CYCLE_MAX - 1;
+    match entity.entity_type {
+        MiscEntityType::SteelThistle => match entity.data {
+            0..STEEL_THISTLE_CYCLE_MAX_MINUS_1 => "+".white().on_black(),
+            STEEL_THISTLE_CYCLE_MAX_MINUS_1 => "+".light_magenta().on_black(),
+            STEEL_THISTLE_CYCLE_MAX => "%".light_magenta().on_black(),
+            _ => unreachable!(),
+        },
+        MiscEntityType::TreasureChest => "=".yellow().on_black(),
+        MiscEntityType::HealingFont => match entity.data {
+            0 => "=".light_green().on_black(),
+            _ => "=".dark_gray().on_black(),
+        },
     }
 }
 
@@ -69,7 +137,7 @@ fn init_test_level(game: &mut GameInstance) {
     game.current_room.create_item(ItemType::ArmadilloFlower, vec2(5, 3));
 }
 
-fn create_lines_for_events<'a, 'b, 'c>(events: &'a [GameEvent], type_table: &'b HashMap<u32, NamedType>) -> Vec<Line<'c>> {
+fn create_lines_for_events<'a>(events: &[GameEvent], type_table: &HashMap<u32, NamedType>) -> Vec<Line<'a>> {
     let player_name = "rodney";
     events.iter().map(|event| {
         let color = match event {
@@ -84,6 +152,9 @@ fn create_lines_for_events<'a, 'b, 'c>(events: &'a [GameEvent], type_table: &'b
             GameEvent::ItemNotEdible { .. } => Color::DarkGray,
             GameEvent::EffectHealed { .. } => Color::LightGreen,
             GameEvent::SlowedByWater { .. } => Color::Cyan,
+            GameEvent::SlidOnIce { .. } => Color::Cyan,
+            GameEvent::WaterFroze => Color::LightCyan,
+            GameEvent::IceMelted => Color::Cyan,
             GameEvent::ActivatedItem { .. } => Color::LightYellow,
             GameEvent::EffectIceDamage { .. } => Color::Red,
             GameEvent::NoEffect { .. } => Color::DarkGray,
@@ -95,6 +166,24 @@ fn create_lines_for_events<'a, 'b, 'c>(events: &'a [GameEvent], type_table: &'b
             GameEvent::EffectStronger { .. } => Color::LightGreen,
             GameEvent::EffectTougher { .. } => Color::LightGreen,
             GameEvent::UsedHealingFont { .. } => Color::LightGreen,
+            GameEvent::Harvested { .. } => Color::LightYellow,
+            GameEvent::NothingToHarvest { .. } => Color::DarkGray,
+            GameEvent::LevelUp { .. } => Color::LightGreen,
+            GameEvent::Rested { .. } => Color::Gray,
+            GameEvent::CantRestNearMonsters => Color::DarkGray,
+            GameEvent::HungerChanged { .. } => Color::Yellow,
+            GameEvent::StarvationDamage { .. } => Color::Red,
+            GameEvent::DroppedLoot { .. } => Color::White,
+            GameEvent::PickedUpItem { .. } => Color::White,
+            GameEvent::Dazzled { .. } => Color::LightYellow,
+            GameEvent::DazzleMiss { .. } => Color::Gray,
+            GameEvent::DazzleFaded { .. } => Color::Gray,
+            GameEvent::MonsterFled { .. } => Color::Yellow,
+            GameEvent::MonsterRallied { .. } => Color::LightRed,
+            GameEvent::EffectSlowed { .. } => Color::Cyan,
+            GameEvent::SpeedReturnedToNormal { .. } => Color::Gray,
+            GameEvent::NoticedPlayer { .. } => Color::LightRed,
+            GameEvent::WokeUp { .. } => Color::Yellow,
             GameEvent::Winner => Color::Magenta,
         };
         let parts = vec![
@@ -109,24 +198,40 @@ pub struct TerminalApp {
     game: GameInstance,
     unread_event_index: usize,
     item_menu: Option<ItemMenu>,
+    // Showing actions for the item under the inventory cursor, rather than picking an item
+    item_details_open: bool,
+    // For choosing from a pile of items
+    pickup_menu: Option<ItemMenu>,
     direction_selection_item: Option<u32>,
+    look_cursor: Option<TilePoint>,
+    character_sheet_open: bool,
+    // Drawn over the map while animations play
+    animation_overlay: HashMap<TilePoint, Span<'static>>,
     exit: bool,
 }
 
 impl TerminalApp {
-    fn new(use_test_level: bool) -> Self {
+    fn new(use_test_level: bool, hunger_enabled: bool, auto_pickup: HashSet<ItemCategory>, animations_enabled: bool) -> Self {
         let mut game = GameInstance::new();
         if use_test_level {
             init_test_level(&mut game);
         } else {
             game.create_first_room();
         }
+        game.hunger_enabled = hunger_enabled;
+        game.auto_pickup = auto_pickup;
+        game.animations_enabled = animations_enabled;
 
         TerminalApp {
             game,
             unread_event_index: 0,
             item_menu: None,
+            item_details_open: false,
+            pickup_menu: None,
             direction_selection_item: None,
+            look_cursor: None,
+            character_sheet_open: false,
+            animation_overlay: HashMap::new(),
             exit: false,
         }
     }
@@ -135,15 +240,100 @@ impl TerminalApp {
         while !self.exit {
             terminal.draw(|frame| self.draw(frame))?;
             self.handle_events()?;
+            self.play_visual_effects(terminal)?;
         }
         Ok(())
     }
 
+    // Plays back what happened during the last command before the resulting state is drawn. The effects are drawn
+    // over the room as it was when they started, so e.g. a thrown javelin isn't already lying where it lands.
+    fn play_visual_effects(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let effects = std::mem::take(&mut self.game.current_room.visual_effects);
+        let before_effects = self.game.current_room.before_visual_effects.take();
+        if !self.game.animations_enabled || effects.is_empty() {
+            return Ok(());
+        }
+        let result_room = before_effects.map(|room| std::mem::replace(&mut self.game.current_room, *room));
+        let result = self.play_effect_frames(terminal, &effects);
+        if let Some(room) = result_room {
+            self.game.current_room = room;
+        }
+        self.animation_overlay.clear();
+        result
+    }
+
+    fn play_effect_frames(&mut self, terminal: &mut DefaultTerminal, effects: &[VisualEffect]) -> Result<()> {
+        for effect in effects.iter() {
+            for (overlay, duration) in self.create_frames_for_effect(effect) {
+                // Nothing to show if it all happened out of sight
+                if overlay.is_empty() {
+                    continue;
+                }
+                self.animation_overlay = overlay;
+                terminal.draw(|frame| self.draw(frame))?;
+                thread::sleep(duration);
+            }
+        }
+        Ok(())
+    }
+
+    fn create_frames_for_effect(&self, effect: &VisualEffect) -> Vec<(HashMap<TilePoint, Span<'static>>, Duration)> {
+        let visible = &self.game.current_room.visible;
+        let single_frame = |position: TilePoint, span: Span<'static>| {
+            let mut overlay = HashMap::new();
+            if visible.contains(&position) {
+                overlay.insert(position, span);
+            }
+            vec![ (overlay, FLASH_FRAME_DURATION) ]
+        };
+        match effect {
+            VisualEffect::Projectile { item_type, path } => {
+                if path.is_empty() {
+                    return vec![];
+                }
+                // Beams stay lit all the way along, thrown items are only ever in one place
+                let is_beam = get_projectile_data(*item_type).is_some_and(|p| p.landing == ProjectileLanding::Dissipates);
+                let span = if !is_beam {
+                    get_char_for_item_type(*item_type)
+                } else if path[0].x == path[path.len() - 1].x {
+                    "|".light_cyan().on_black()
+                } else {
+                    "-".light_cyan().on_black()
+                };
+                (0..path.len()).map(|i| {
+                    let start = if is_beam { 0 } else { i };
+                    let overlay = path[start..=i].iter()
+                        .filter(|position| visible.contains(position))
+                        .map(|&position| (position, span.clone()))
+                        .collect();
+                    (overlay, PROJECTILE_FRAME_DURATION)
+                }).collect()
+            },
+            VisualEffect::Hit { position } => {
+                let span = self.get_char_for_cell_contents(*position);
+                single_frame(*position, Span::styled(span.content.into_owned(), span.style).on_red())
+            },
+            VisualEffect::ThistleStrike { position } => single_frame(*position, "%".white().on_magenta()),
+        }
+    }
+
     fn draw(&self, frame: &mut Frame) {
         frame.render_widget(self, frame.area());
     }
 
     fn get_char_for_cell(&self, position: TilePoint) -> Span<'_> {
+        if let Some(span) = self.animation_overlay.get(&position) {
+            return span.clone();
+        }
+        let span = self.get_char_for_cell_contents(position);
+        if self.look_cursor == Some(position) {
+            span.reversed()
+        } else {
+            span
+        }
+    }
+
+    fn get_char_for_cell_contents(&self, position: TilePoint) -> Span<'_> {
         let visible = self.game.current_room.visible.contains(&position);
         let explored = self.game.current_room.explored.contains(&position);
         if !visible && !explored {
@@ -151,70 +341,50 @@ impl TerminalApp {
         }
 
         if visible {
-            let mut actors = self.game.current_room.find_actors_at(position, true);
-            if actors.len() > 0 {
-                // Sort so alive actors are displayed above dead actors
-                actors.sort_by_key(|&index| self.game.current_room.actors[index].is_dead);
+            let actors = self.game.current_room.find_actors_at(position, false);
+            if !actors.is_empty() {
                 let actor_index = actors[0];
                 let actor = &self.game.current_room.actors[actor_index];
-                let mut c = match actor.actor_type {
-                    ActorType::Player => "@".light_yellow().on_black(),
-                    ActorType::Toad => "t".light_green().on_black(),
-                    ActorType::MouseWarrior => "m".light_cyan().on_black(),
-                    ActorType::MouseSkirmisher => "m".magenta().on_black(),
-                    ActorType::ToothyStarling => "s".cyan().on_black(),
-                    ActorType::DustySkeleton => "z".white().on_black(),
-                    ActorType::BlueJelly => "j".light_blue().on_black(),
-                };
-                if actor.is_dead {
-                    c = c.dark_gray();
-                } else if actor.current_hp <= (actor.max_hp as f32 / 4.0).round() as i32 {
+                let mut c = get_char_for_actor_type(actor.actor_type);
+                if actor.is_harvested() {
+                    c = c.green();
+                } else if actor.current_hp <= (actor.max_hp() as f32 / 4.0).round() as i32 {
                     c = c.red();
-                } else if actor.current_hp <= (actor.max_hp as f32 / 2.0).round() as i32 {
+                } else if actor.current_hp <= (actor.max_hp() as f32 / 2.0).round() as i32 {
                     c = c.light_red();
                 }
                 return c;
             }
 
+            // Items are drawn above corpses, so carcasses and dropped loot show up
             let items = self.game.current_room.find_loose_items_at(position);
-            if items.len() > 0 {
+            if !items.is_empty() {
                 let item_index = items[0];
                 let item = &self.game.current_room.items[item_index];
-                return match item.item_type {
-                    ItemType::LumpOfBlackstone => "*".gray().on_black(),
-                    ItemType::BlackstoneSpear => "|".gray().on_black(),
-                    ItemType::CarmineSword => "\\".red().on_black(),
-                    ItemType::MoonlightKnife => "-".white().on_black(),
-                    ItemType::BoneLamellar => "[".white().on_black(),
-                    ItemType::FeatheredCavalier => "^".yellow().on_black(),
-                    ItemType::CarmineHelm => "^".red().on_black(),
-                    ItemType::CarmineChainmail => "[".red().on_black(),
-                    ItemType::Bloodflower => "%".light_red().on_black(),
-                    ItemType::ElephantFrond => "%".light_green().on_black(),
-                    ItemType::ArmadilloFlower => "%".light_yellow().on_black(),
-                    ItemType::WandOfIce => "/".light_cyan().on_black(),
-                };
+                return get_char_for_item_type(item.item_type);
+            }
+
+            let dead_actors = self.game.current_room.find_actors_at(position, true);
+            if !dead_actors.is_empty() {
+                return get_char_for_actor_type(self.game.current_room.actors[dead_actors[0]].actor_type).dark_gray();
             }
 
             let misc_entities = self.game.current_room.find_misc_entities_at(position);
-            if misc_entities.len() > 0 {
+            if !misc_entities.is_empty() {
                 let entity_index = misc_entities[0];
                 let entity = &self.game.current_room.misc_entities[entity_index];
-                const STEEL_THISTLE_CYCLE_MAX_MINUS_1: i32 = STEEL_THISTLE_CYCLE_MAX - 1;
-                return match entity.entity_type {
-                    MiscEntityType::SteelThistle => match entity.data {
-                        0..STEEL_THISTLE_CYCLE_MAX_MINUS_1 => "+".white().on_black(),
-                        STEEL_THISTLE_CYCLE_MAX_MINUS_1 => "+".light_magenta().on_black(),
-                        STEEL_THISTLE_CYCLE_MAX => "%".light_magenta().on_black(),
-                        _ => unreachable!(),
-                    },
-                    MiscEntityType::TreasureChest => "=".yellow().on_black(),
-                    MiscEntityType::HealingFont => match entity.data {
-                        0 => "=".light_green().on_black(),
-                        _ => "=".dark_gray().on_black(),
-                    },
-                };
+                return get_char_for_misc_entity(entity);
             }
+        } else if let Some(memory) = self.game.current_room.memory.get(&position) {
+            // Dimmed, since it may not be there any more
+            let remembered = if let Some(actor) = memory.actor {
+                get_char_for_actor_type(actor.actor_type)
+            } else if let Some(item_type) = memory.item_type {
+                get_char_for_item_type(item_type)
+            } else {
+                get_char_for_misc_entity(memory.misc_entity.as_ref().unwrap())
+            };
+            return remembered.dark_gray();
         }
 
         let display = display_for_cell_type(self.game.current_room.get_cell_type(position));
@@ -230,13 +400,56 @@ impl TerminalApp {
         Span::styled(display.c.to_string(), Style::default().fg(fg_color).bg(bg_color))
     }
 
+    fn create_lines_for_look(&self, position: TilePoint, type_table: &HashMap<u32, NamedType>) -> Vec<Line<'_>> {
+        let room = &self.game.current_room;
+        let mut names = vec![];
+        if room.visible.contains(&position) {
+            for actor_index in room.find_actors_at(position, true) {
+                let actor = &room.actors[actor_index];
+                let name = if actor.actor_type == ActorType::Player {
+                    "you".to_string()
+                } else if actor.is_dead {
+                    format!("dead {}", strings::actor_type_to_name(actor.actor_type))
+                } else if actor.has_awareness() {
+                    format!("{} ({})", strings::actor_type_to_name(actor.actor_type), strings::awareness_to_name(actor.awareness))
+                } else {
+                    strings::actor_type_to_name(actor.actor_type).to_string()
+                };
+                names.push(name);
+            }
+            for item_index in room.find_loose_items_at(position) {
+                names.push(strings::get_item_name(room.items[item_index].id, type_table));
+            }
+            for entity_index in room.find_misc_entities_at(position) {
+                names.push(strings::misc_entity_type_to_name(room.misc_entities[entity_index].entity_type).to_string());
+            }
+        } else if let Some(memory) = room.memory.get(&position) {
+            if let Some(actor) = memory.actor {
+                let turns_ago = self.game.turn - actor.turn_seen;
+                names.push(format!("{} (seen {} turns ago)", strings::actor_type_to_name(actor.actor_type), turns_ago));
+            }
+            if let Some(item_type) = memory.item_type {
+                names.push(format!("{} (remembered)", strings::item_type_to_name(item_type)));
+            }
+            if let Some(entity) = &memory.misc_entity {
+                names.push(format!("{} (remembered)", strings::misc_entity_type_to_name(entity.entity_type)));
+            }
+        }
+        if room.visible.contains(&position) || room.explored.contains(&position) {
+            names.push(strings::get_terrain_description(room.get_cell_type(position)));
+        } else {
+            names.push(strings::UNEXPLORED_LOOK_TEXT.to_string());
+        }
+        names.into_iter().map(|name| Line::from(vec![ "x> ".light_yellow(), name.white() ])).collect()
+    }
+
     fn build_type_table(&self) -> HashMap<u32, NamedType> {
         let mut result = HashMap::new();
         for actor in self.game.current_room.actors.iter() {
             result.insert(actor.id, NamedType::ActorType { actor_type: actor.actor_type });
         }
         for item in self.game.current_room.items.iter() {
-            result.insert(item.id, NamedType::ItemType { item_type: item.item_type });
+            result.insert(item.id, NamedType::ItemType { item_type: item.item_type, quantity: item.quantity });
         }
         result
     }
@@ -244,22 +457,31 @@ impl TerminalApp {
     fn walk_or_fight(&mut self, delta: TileDelta) {
         let next_position = self.game.current_room.get_player().position + delta;
         let other_actors = self.game.current_room.find_actors_at(next_position, false);
-        if other_actors.len() > 0 {
+        if !other_actors.is_empty() {
             self.game.execute_command(Command::Fight { delta });
         } else {
             self.game.execute_command(Command::Walk { delta });
         }
     }
 
-    fn get_first_item(&mut self) {
-        let position = self.game.current_room.get_player().position;
-        let items = self.game.current_room.find_loose_items_at(position);
-        if items.len() > 0 {
-            let item_id = self.game.current_room.items[items[0]].id;
-            self.game.execute_command(Command::GetItem { item_id });
+    // Piles get a menu, single items are picked up right away
+    fn pick_up(&mut self) {
+        let room = &self.game.current_room;
+        let item_ids = room.find_loose_items_at(room.get_player().position).into_iter()
+            .map(|i| room.items[i].id)
+            .collect::<Vec<u32>>();
+        match item_ids.len() {
+            0 => (),
+            1 => self.game.execute_command(Command::GetItems { item_ids: vec![ item_ids[0] ] }),
+            _ => self.pickup_menu = Some(ItemMenu::new(item_ids)),
         }
     }
 
+    fn take_items(&mut self, item_ids: Vec<u32>) {
+        self.pickup_menu = None;
+        self.game.execute_command(Command::GetItems { item_ids });
+    }
+
     fn handle_key_main_screen(&mut self, key_code: KeyCode) {
         match key_code {
             KeyCode::Char('q') => self.exit = true,
@@ -268,11 +490,14 @@ impl TerminalApp {
             KeyCode::Up | KeyCode::Char('k') => self.walk_or_fight(vec2(0, -1)),
             KeyCode::Down | KeyCode::Char('j') => self.walk_or_fight(vec2(0, 1)),
             KeyCode::Char('.') => self.game.execute_command(Command::Wait),
-            KeyCode::Char('g') | KeyCode::Char(',') => self.get_first_item(),
+            KeyCode::Char('r') => self.game.rest(),
+            KeyCode::Char('g') | KeyCode::Char(',') => self.pick_up(),
             KeyCode::Char('i') => {
-                let item_ids = self.game.current_room.player_inventory.clone();
-                self.item_menu = Some(ItemMenu::new(item_ids));
+                self.item_menu = Some(ItemMenu::new(self.get_sorted_inventory()));
+                self.item_details_open = false;
             },
+            KeyCode::Char('x') => self.look_cursor = Some(self.game.current_room.get_player().position),
+            KeyCode::Char('c') => self.character_sheet_open = true,
             _ => {}
         }
     }
@@ -285,28 +510,96 @@ impl TerminalApp {
         Some(item_menu.item_ids[item_menu.cursor_index])
     }
 
+    // Grouped by category, then in letter order
+    fn get_sorted_inventory(&self) -> Vec<u32> {
+        let room = &self.game.current_room;
+        let mut item_ids = room.player_inventory.clone();
+        item_ids.sort_by_key(|&item_id| {
+            let item = room.get_item(item_id);
+            (room.get_item_data(item_id).category, item.inventory_letter.is_none(), item.inventory_letter)
+        });
+        item_ids
+    }
+
     fn handle_key_item_menu(&mut self, key_code: KeyCode) {
+        if self.item_details_open {
+            self.handle_key_item_details(key_code);
+            return;
+        }
         match key_code {
-            KeyCode::Char('q') => self.exit = true,
-            KeyCode::Up | KeyCode::Char('k') => self.item_menu.as_mut().unwrap().move_cursor(-1),
-            KeyCode::Down | KeyCode::Char('j') => self.item_menu.as_mut().unwrap().move_cursor(1),
-            KeyCode::Char('d') => if let Some(item_id) = self.get_selected_item_id() {
-                self.game.execute_command(Command::DropItem { item_id });
-                self.item_menu = None;
+            KeyCode::Up => self.item_menu.as_mut().unwrap().move_cursor(-1),
+            KeyCode::Down => self.item_menu.as_mut().unwrap().move_cursor(1),
+            KeyCode::Enter => self.item_details_open = self.get_selected_item_id().is_some(),
+            KeyCode::Char(c) => {
+                let room = &self.game.current_room;
+                let item_menu = self.item_menu.as_mut().unwrap();
+                let lettered_item = item_menu.item_ids.iter().copied().find(|&item_id| room.get_item(item_id).inventory_letter == Some(c));
+                if let Some(item_id) = lettered_item {
+                    item_menu.move_cursor_to(item_id);
+                    self.item_details_open = true;
+                }
             },
-            KeyCode::Char('w') => if let Some(item_id) = self.get_selected_item_id() {
-                self.game.execute_command(Command::ToggleEquipment { item_id });
-                self.item_menu = None;
+            KeyCode::Esc => self.item_menu = None,
+            _ => {}
+        }
+    }
+
+    fn handle_key_pickup_menu(&mut self, key_code: KeyCode) {
+        let pickup_menu = self.pickup_menu.as_mut().unwrap();
+        match key_code {
+            KeyCode::Up => pickup_menu.move_cursor(-1),
+            KeyCode::Down => pickup_menu.move_cursor(1),
+            KeyCode::Char(' ') => {
+                let item_id = pickup_menu.item_ids[pickup_menu.cursor_index];
+                pickup_menu.toggle_selected(item_id);
             },
-            KeyCode::Char('e') => if let Some(item_id) = self.get_selected_item_id() {
-                self.game.execute_command(Command::EatItem { item_id });
-                self.item_menu = None;
+            KeyCode::Char(',') => {
+                let item_ids = pickup_menu.item_ids.clone();
+                self.take_items(item_ids);
+            },
+            KeyCode::Enter => {
+                // Just the item under the cursor if nothing's been selected
+                let item_ids = if pickup_menu.selected.is_empty() {
+                    vec![ pickup_menu.item_ids[pickup_menu.cursor_index] ]
+                } else {
+                    pickup_menu.item_ids.iter().copied().filter(|item_id| pickup_menu.selected.contains(item_id)).collect()
+                };
+                self.take_items(item_ids);
             },
-            KeyCode::Char('v') | KeyCode::Char('t') => if let Some(item_id) = self.get_selected_item_id() {
-                self.direction_selection_item = Some(item_id);
+            KeyCode::Char(c @ 'a'..='z') => {
+                let index = (c as u8 - b'a') as usize;
+                if index < pickup_menu.item_ids.len() {
+                    pickup_menu.cursor_index = index;
+                    pickup_menu.toggle_selected(pickup_menu.item_ids[index]);
+                }
+            },
+            KeyCode::Esc => self.pickup_menu = None,
+            _ => {}
+        }
+    }
+
+    fn handle_key_item_details(&mut self, key_code: KeyCode) {
+        let item_id = self.get_selected_item_id().unwrap();
+        let item = self.game.current_room.get_item(item_id);
+        let actions = get_item_actions(item.item_type, item.quantity);
+        match key_code {
+            KeyCode::Up => self.item_menu.as_mut().unwrap().move_cursor(-1),
+            KeyCode::Down => self.item_menu.as_mut().unwrap().move_cursor(1),
+            KeyCode::Esc => self.item_details_open = false,
+            KeyCode::Char(c) => {
+                let Some(&action) = actions.iter().find(|&&action| get_key_for_item_action(action) == c) else {
+                    return;
+                };
+                match action {
+                    ItemAction::Equip => self.game.execute_command(Command::ToggleEquipment { item_id }),
+                    ItemAction::Eat => self.game.execute_command(Command::EatItem { item_id }),
+                    ItemAction::Throw | ItemAction::Evoke => self.direction_selection_item = Some(item_id),
+                    ItemAction::Drop => self.game.execute_command(Command::DropItem { item_id, quantity: None }),
+                    ItemAction::DropOne => self.game.execute_command(Command::DropItem { item_id, quantity: Some(1) }),
+                }
                 self.item_menu = None;
+                self.item_details_open = false;
             },
-            KeyCode::Esc | KeyCode::Char('i') => self.item_menu = None,
             _ => {}
         }
     }
@@ -335,15 +628,52 @@ impl TerminalApp {
         }
     }
 
+    fn move_look_cursor(&mut self, delta: TileDelta) {
+        let room = &self.game.current_room;
+        let cursor = self.look_cursor.unwrap() + delta;
+        if cursor.x >= 0 && cursor.y >= 0 && (cursor.x as usize) < room.size.x && (cursor.y as usize) < room.size.y {
+            self.look_cursor = Some(cursor);
+        }
+    }
+
+    fn handle_key_look(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Char('q') => self.exit = true,
+            KeyCode::Left | KeyCode::Char('h') => self.move_look_cursor(vec2(-1, 0)),
+            KeyCode::Right | KeyCode::Char('l') => self.move_look_cursor(vec2(1, 0)),
+            KeyCode::Up | KeyCode::Char('k') => self.move_look_cursor(vec2(0, -1)),
+            KeyCode::Down | KeyCode::Char('j') => self.move_look_cursor(vec2(0, 1)),
+            KeyCode::Esc | KeyCode::Char('x') => self.look_cursor = None,
+            _ => {}
+        }
+    }
+
+    fn handle_key_character_sheet(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Char('q') => self.exit = true,
+            KeyCode::Esc | KeyCode::Char('c') => self.character_sheet_open = false,
+            _ => {}
+        }
+    }
+
     fn handle_key_game_over(&mut self, key_code: KeyCode) {
         match key_code {
             KeyCode::Char('q') => self.exit = true,
             KeyCode::Char('r') => {
-                self.game = GameInstance::new();
-                self.game.create_first_room();
+                // Game mode options carry over to the new game
+                let mut game = GameInstance::new();
+                game.hunger_enabled = self.game.hunger_enabled;
+                game.auto_pickup = std::mem::take(&mut self.game.auto_pickup);
+                game.animations_enabled = self.game.animations_enabled;
+                game.create_first_room();
+                self.game = game;
                 self.unread_event_index = 0;
                 self.item_menu = None;
+                self.item_details_open = false;
+                self.pickup_menu = None;
                 self.direction_selection_item = None;
+                self.look_cursor = None;
+                self.character_sheet_open = false;
             },
             _ => {}
         }
@@ -352,10 +682,16 @@ impl TerminalApp {
     fn handle_key_event(&mut self, key_event: KeyEvent) {
         if self.game.game_over {
             self.handle_key_game_over(key_event.code);
+        } else if self.pickup_menu.is_some() {
+            self.handle_key_pickup_menu(key_event.code);
         } else if self.item_menu.is_some() {
             self.handle_key_item_menu(key_event.code);
         } else if self.direction_selection_item.is_some() {
             self.handle_key_direction_selection(key_event.code);
+        } else if self.look_cursor.is_some() {
+            self.handle_key_look(key_event.code);
+        } else if self.character_sheet_open {
+            self.handle_key_character_sheet(key_event.code);
         } else {
             self.handle_key_main_screen(key_event.code);
         }
@@ -376,16 +712,25 @@ impl TerminalApp {
         Ok(())
     }
 
-    fn render_main_screen(&self, buf: &mut Buffer) {
+    fn render_main_screen(&self, area: Rect, buf: &mut Buffer) {
         let map_block = Block::bordered()
             .padding(Padding::uniform(1))
             .border_type(ratatui::widgets::BorderType::Thick)
             .title(Line::from(" Lark ".bold()).centered());
 
+        let [map_area, side_hud_area] = Layout::horizontal([Constraint::Fill(1), Constraint::Length(SIDE_HUD_WIDTH)]).areas(area);
+        let view_size = map_block.inner(map_area);
+        let room_size = self.game.current_room.size;
+        let focus = self.look_cursor.unwrap_or(self.game.current_room.get_player().position);
+        let offset_x = ui_common::scroll_offset(view_size.width as i32, room_size.x as i32, focus.x);
+        let offset_y = ui_common::scroll_offset(view_size.height as i32, room_size.y as i32, focus.y);
+        let visible_width = (view_size.width as i32).min(room_size.x as i32);
+        let visible_height = (view_size.height as i32).min(room_size.y as i32);
+
         let mut lines_vec = vec![];
-        for y in 0..(self.game.current_room.size.y as i32) {
+        for y in offset_y..(offset_y + visible_height) {
             let mut char_vec = vec![];
-            for x in 0..(self.game.current_room.size.x as i32) {
+            for x in offset_x..(offset_x + visible_width) {
                 char_vec.push(self.get_char_for_cell(vec2(x, y)));
             }
             lines_vec.push(Line::from(char_vec));
@@ -394,41 +739,86 @@ impl TerminalApp {
         Paragraph::new(map_text)
             .centered()
             .block(map_block)
-            .render(Rect::new(0, 0, 48, MAIN_AREA_HEIGHT), buf);
+            .render(map_area, buf);
 
         let side_hud_block = Block::new()
             .padding(Padding::symmetric(2, 1));
-        let player_ref = self.game.current_room.get_player();
-        let side_hud_lines = vec![
-            Line::from(format!("Depth {}", self.game.current_room.depth + 1)),
+        let room = &self.game.current_room;
+        let player_ref = room.get_player();
+        let mut side_hud_lines = vec![
+            Line::from(format!("Depth {}", room.depth + 1)),
+            Line::from(format!("Level {}", player_ref.level)),
+            Line::from(match get_next_level_data(player_ref.level) {
+                Some(level_data) => format!("XP {}/{}", player_ref.experience, level_data.experience_required),
+                None => format!("XP {}", player_ref.experience),
+            }),
             Line::from(""),
-            Line::from(format!("Health {}/{}", player_ref.current_hp, player_ref.max_hp)),
-            Line::from(format!("Attack {}", player_ref.attack_power)),
-            Line::from(format!("Defense {}", player_ref.defense_power)),
+            Line::from(format!("Health {}/{}", player_ref.current_hp, player_ref.max_hp())),
+            Line::from(format!("Attack {}", player_ref.attack_power())),
+            Line::from(format!("Defense {}", player_ref.defense_power())),
+            Line::from(format!("Stealth {}", player_ref.stealth)),
         ];
+        if self.game.hunger_enabled {
+            let hunger = get_hunger_state(player_ref.satiety);
+            if hunger != HungerState::Fed {
+                side_hud_lines.push(Line::from(strings::hunger_state_to_name(hunger)));
+            }
+        }
+        if player_ref.dazzled_turns > 0 {
+            side_hud_lines.push(Line::from("dazzled"));
+        }
+        if let Some(condition) = player_ref.speed_condition {
+            side_hud_lines.push(Line::from(strings::speed_condition_to_name(condition)));
+        }
+        side_hud_lines.push(Line::from(""));
+        // Monsters in view and what they're up to
+        for actor in room.actors.iter() {
+            if actor.has_awareness() && !actor.is_dead && room.visible.contains(&actor.position) {
+                side_hud_lines.push(Line::from(vec![
+                    self.get_char_for_cell_contents(actor.position),
+                    Span::from(format!(" {}", strings::awareness_to_name(actor.awareness))),
+                ]));
+            }
+        }
         Paragraph::new(Text::from(side_hud_lines))
             .block(side_hud_block)
-            .render(Rect::new(48, 0, 16, MAIN_AREA_HEIGHT), buf);
+            .render(side_hud_area, buf);
     }
 
-    fn render_item_menu(&self, buf: &mut Buffer, type_table: &HashMap<u32, NamedType>) {
+    fn render_item_menu(&self, area: Rect, buf: &mut Buffer, type_table: &HashMap<u32, NamedType>) {
         let menu_block = Block::bordered()
             .padding(Padding::uniform(1))
             .border_type(ratatui::widgets::BorderType::Thick)
             .title(Line::from(" Inventory ".bold()).centered());
 
+        let room = &self.game.current_room;
         let item_menu = self.item_menu.as_ref().unwrap();
         let mut lines_vec = vec![];
         if item_menu.is_empty() {
             lines_vec.push(Line::from(strings::EMPTY_INVENTORY.white()));
         } else {
+            let mut current_category = None;
             for i in 0..item_menu.item_ids.len() {
                 let item_id = item_menu.item_ids[i];
-                let s = if self.game.current_room.get_item(item_id).equipped {
-                    format!("{} ({})", strings::get_item_name(item_id, type_table), strings::get_equipped_participle(item_id, type_table))
-                } else {
-                    strings::get_item_name(item_id, type_table).to_string()
+                let item = room.get_item(item_id);
+                let category = room.get_item_data(item_id).category;
+                if current_category != Some(category) {
+                    if current_category.is_some() {
+                        lines_vec.push(Line::from(""));
+                    }
+                    lines_vec.push(Line::from(strings::item_category_to_name(category).bold()));
+                    current_category = Some(category);
+                }
+                let mut s = match item.inventory_letter {
+                    Some(letter) => format!("{} - {}", letter, strings::get_item_name(item_id, type_table)),
+                    None => format!("    {}", strings::get_item_name(item_id, type_table)),
                 };
+                if let Some(charges) = item.wand_charges {
+                    s += &format!(" ({} charges)", charges);
+                }
+                if item.equipped {
+                    s += &format!(" ({})", strings::get_equipped_participle(item_id, type_table));
+                }
                 let mut span = Span::from(s);
                 if i == item_menu.cursor_index {
                     span = span.black().on_white();
@@ -439,21 +829,213 @@ impl TerminalApp {
             }
         }
 
+        if item_menu.is_empty() {
+            Paragraph::new(Text::from(lines_vec))
+                .left_aligned()
+                .block(menu_block)
+                .render(area, buf);
+            return;
+        }
+        let [list_area, details_area] = Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+        Paragraph::new(Text::from(lines_vec))
+            .left_aligned()
+            .block(menu_block)
+            .render(list_area, buf);
+        self.render_item_details(item_menu.item_ids[item_menu.cursor_index], details_area, buf, type_table);
+    }
+
+    fn render_pickup_menu(&self, area: Rect, buf: &mut Buffer, type_table: &HashMap<u32, NamedType>) {
+        let menu_block = Block::bordered()
+            .padding(Padding::uniform(1))
+            .border_type(ratatui::widgets::BorderType::Thick)
+            .title(Line::from(" Pick up ".bold()).centered());
+
+        let pickup_menu = self.pickup_menu.as_ref().unwrap();
+        let mut lines_vec = vec![];
+        for (i, &item_id) in pickup_menu.item_ids.iter().enumerate() {
+            let letter = if i < 26 { (b'a' + i as u8) as char } else { ' ' };
+            let check = if pickup_menu.selected.contains(&item_id) { 'x' } else { ' ' };
+            let mut span = Span::from(format!("{} [{}] {}", letter, check, strings::get_item_name(item_id, type_table)));
+            if i == pickup_menu.cursor_index {
+                span = span.black().on_white();
+            } else {
+                span = span.white();
+            }
+            lines_vec.push(Line::from(span));
+        }
+
         Paragraph::new(Text::from(lines_vec))
             .left_aligned()
             .block(menu_block)
-            .render(Rect::new(0, 0, 64, MAIN_AREA_HEIGHT), buf);
+            .render(area, buf);
+    }
+
+    fn render_item_details(&self, item_id: u32, area: Rect, buf: &mut Buffer, type_table: &HashMap<u32, NamedType>) {
+        let details_block = Block::bordered()
+            .padding(Padding::uniform(1))
+            .border_type(ratatui::widgets::BorderType::Thick)
+            .title(Line::from(format!(" {} ", strings::get_item_name(item_id, type_table)).bold()).centered());
+
+        let room = &self.game.current_room;
+        let item = room.get_item(item_id);
+        let item_data = room.get_item_data(item_id);
+        let mut lines_vec = vec![
+            Line::from(strings::item_type_to_description(item.item_type).white()),
+            Line::from(""),
+        ];
+        if item_data.category == ItemCategory::Weapon || item_data.category == ItemCategory::Armor {
+            lines_vec.push(Line::from(format!("Slot: {}", strings::equip_slot_to_name(item_data.equip_slot)).white()));
+        }
+        if let Some(bonuses) = describe_stat_bonus(get_item_stat_bonus(item_data)) {
+            lines_vec.push(Line::from(format!("Bonuses: {}", bonuses).white()));
+        }
+        if let Some(attack_cost) = item_data.attack_cost {
+            lines_vec.push(Line::from(format!("Attack time: {}%", attack_cost * 100 / ACTION_COST).white()));
+        }
+        if let Some(projectile) = get_projectile_data(item.item_type) {
+            lines_vec.push(Line::from(format!("Range: {}", projectile.range).white()));
+        }
+        if let Some(charges) = item.wand_charges {
+            lines_vec.push(Line::from(format!("Charges: {}", charges).white()));
+        }
+        if let Some(nutrition) = item_data.nutrition.filter(|_| self.game.hunger_enabled) {
+            lines_vec.push(Line::from(format!("Nutrition: {}", nutrition).white()));
+        }
+        if item.equipped {
+            lines_vec.push(Line::from(format!("Currently {}", strings::get_equipped_participle(item_id, type_table)).white()));
+        }
+
+        Paragraph::new(Text::from(lines_vec))
+            .left_aligned()
+            .wrap(Wrap { trim: true })
+            .block(details_block)
+            .render(area, buf);
+    }
+
+    fn render_character_sheet(&self, area: Rect, buf: &mut Buffer, type_table: &HashMap<u32, NamedType>) {
+        let sheet_block = Block::bordered()
+            .padding(Padding::uniform(1))
+            .border_type(ratatui::widgets::BorderType::Thick)
+            .title(Line::from(" Character ".bold()).centered());
+
+        let room = &self.game.current_room;
+        let player = room.get_player();
+        let mut lines_vec = vec![
+            Line::from(format!("Level {}, turn {}", player.level, self.game.turn).white()),
+            Line::from(""),
+            Line::from("Equipment".bold()),
+        ];
+        for &slot in ALL_EQUIP_SLOTS.iter() {
+            let equipped_id = room.player_inventory.iter().copied()
+                .find(|&item_id| room.get_item(item_id).equipped && room.get_item_data(item_id).equip_slot == slot);
+            let description = match equipped_id {
+                Some(item_id) => {
+                    let name = strings::get_item_name(item_id, type_table);
+                    match describe_stat_bonus(get_item_stat_bonus(room.get_item_data(item_id))) {
+                        Some(bonuses) => format!("{} ({})", name, bonuses),
+                        None => name.to_string(),
+                    }
+                },
+                None => strings::NOTHING_EQUIPPED.to_string(),
+            };
+            lines_vec.push(Line::from(format!("{:<8}{}", strings::equip_slot_to_name(slot), description).white()));
+        }
+
+        lines_vec.push(Line::from(""));
+        lines_vec.push(Line::from(format!("{:<9}{:>6}{:>6}{:>6}{:>6}{:>6}", "", "base", "level", "buffs", "gear", "total").bold()));
+        let stats = &player.stats;
+        let sources = [ stats.base, stats.level, stats.buffs, stats.gear, stats.total() ];
+        let rows = [
+            ("Health", sources.map(|bonus| bonus.max_hp)),
+            ("Attack", sources.map(|bonus| bonus.attack)),
+            ("Defense", sources.map(|bonus| bonus.defense)),
+        ];
+        for (name, [base, level, buffs, gear, total]) in rows {
+            lines_vec.push(Line::from(format!("{:<9}{:>6}{:>6}{:>6}{:>6}{:>6}", name, base, level, buffs, gear, total).white()));
+        }
+
+        lines_vec.push(Line::from(""));
+        lines_vec.push(Line::from("Conditions".bold()));
+        let mut conditions = vec![];
+        if self.game.hunger_enabled {
+            conditions.push(strings::hunger_state_to_name(get_hunger_state(player.satiety)).to_string());
+        }
+        if player.dazzled_turns > 0 {
+            conditions.push(format!("dazzled ({} turns)", player.dazzled_turns));
+        }
+        if let Some(condition) = player.speed_condition {
+            conditions.push(format!("{} ({} turns)", strings::speed_condition_to_name(condition), player.speed_condition_turns));
+        }
+        if conditions.is_empty() {
+            conditions.push("none".to_string());
+        }
+        lines_vec.extend(conditions.into_iter().map(|condition| Line::from(condition.white())));
+
+        Paragraph::new(Text::from(lines_vec))
+            .left_aligned()
+            .block(sheet_block)
+            .render(area, buf);
+    }
+}
+
+// E.g. "+2 attack, +1 defense", or None for items that don't change stats
+fn describe_stat_bonus(bonus: StatBonus) -> Option<String> {
+    let mut parts = vec![];
+    for (amount, name) in [ (bonus.attack, "attack"), (bonus.defense, "defense"), (bonus.max_hp, "health") ] {
+        if amount != 0 {
+            parts.push(format!("{:+} {}", amount, name));
+        }
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+fn get_key_for_item_action(action: ItemAction) -> char {
+    match action {
+        ItemAction::Equip => 'w',
+        ItemAction::Eat => 'e',
+        ItemAction::Throw => 't',
+        ItemAction::Evoke => 'v',
+        ItemAction::Drop => 'd',
+        ItemAction::DropOne => 'D',
     }
 }
 
 impl Widget for &TerminalApp {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < MIN_TERMINAL_WIDTH || area.height < MIN_TERMINAL_HEIGHT {
+            let [_, message_area, _] = Layout::vertical([Constraint::Fill(1), Constraint::Length(2), Constraint::Fill(1)]).areas(area);
+            let message_lines = vec![
+                Line::from(strings::TERMINAL_TOO_SMALL.light_yellow()),
+                Line::from(format!("{}x{}, need {}x{}", area.width, area.height, MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT).dark_gray()),
+            ];
+            Paragraph::new(Text::from(message_lines))
+                .centered()
+                .wrap(Wrap { trim: true })
+                .render(message_area, buf);
+            return;
+        }
+
+        let [main_area, event_area, reminder_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(EVENT_AREA_HEIGHT),
+            Constraint::Length(REMINDER_HEIGHT),
+        ]).areas(area);
+        let [reminder_area, second_reminder_area] = Layout::vertical([Constraint::Length(1); 2]).areas(reminder_area);
+
         let type_table = self.build_type_table();
 
-        if self.item_menu.is_some() {
-            self.render_item_menu(buf, &type_table);
+        if self.pickup_menu.is_some() {
+            self.render_pickup_menu(main_area, buf, &type_table);
+        } else if self.item_menu.is_some() {
+            self.render_item_menu(main_area, buf, &type_table);
+        } else if self.character_sheet_open {
+            self.render_character_sheet(main_area, buf, &type_table);
         } else {
-            self.render_main_screen(buf);
+            self.render_main_screen(main_area, buf);
         }
 
         let lines = if self.direction_selection_item.is_some() {
@@ -462,15 +1044,17 @@ impl Widget for &TerminalApp {
                 strings::DIRECTION_SELECTION_PROMPT.white(),
             ];
             vec![ Line::from(parts) ]
+        } else if let Some(look_cursor) = self.look_cursor {
+            self.create_lines_for_look(look_cursor, &type_table)
         } else {
             let unread_events = &self.game.event_log[self.unread_event_index..];
-            if unread_events.len() > 0 {
-                create_lines_for_events(&unread_events, &type_table)
+            if !unread_events.is_empty() {
+                create_lines_for_events(unread_events, &type_table)
             } else {
                 vec![]
             }
         };
-        if lines.len() > 0 {
+        if !lines.is_empty() {
             let event_block = Block::bordered()
                 .padding(Padding::horizontal(1))
                 .border_type(ratatui::widgets::BorderType::Thick);
@@ -479,29 +1063,80 @@ impl Widget for &TerminalApp {
             Paragraph::new(Text::from(lines))
                 .left_aligned()
                 .block(event_block)
-                .render(Rect::new(0, MAIN_AREA_HEIGHT, 64, height), buf);
+                .render(Rect { height, ..event_area }, buf);
         }
 
-        let reminder_y = area.height - 2;
         if self.game.game_over {
             Line::from("'q' = quit, 'r' = restart".dark_gray())
-                .render(Rect::new(0, reminder_y, 64, 1), buf);
+                .render(reminder_area, buf);
+        } else if self.pickup_menu.is_some() {
+            Line::from("letter/space = select, enter = take selected, ',' = take all,".dark_gray())
+                .render(reminder_area, buf);
+            Line::from("arrow keys = move, esc = cancel".dark_gray())
+                .render(second_reminder_area, buf);
+        } else if self.item_menu.is_some() && self.item_details_open {
+            let item_id = self.get_selected_item_id().unwrap();
+            let item = self.game.current_room.get_item(item_id);
+            let mut parts = get_item_actions(item.item_type, item.quantity).into_iter().map(|action| {
+                let name = match action {
+                    ItemAction::Equip => match (item.equipped, self.game.current_room.get_item_data(item_id).category) {
+                        (false, ItemCategory::Weapon) => "wield",
+                        (false, _) => "wear",
+                        (true, ItemCategory::Weapon) => "put away",
+                        (true, _) => "take off",
+                    },
+                    ItemAction::Eat => "eat",
+                    ItemAction::Throw => "throw",
+                    ItemAction::Evoke => "evoke",
+                    ItemAction::Drop => "drop",
+                    ItemAction::DropOne => "drop one",
+                };
+                format!("'{}' = {}", get_key_for_item_action(action), name)
+            }).collect::<Vec<String>>();
+            parts.push("esc = back".to_string());
+            Line::from(parts.join(", ").dark_gray())
+                .render(reminder_area, buf);
         } else if self.item_menu.is_some() {
-            Line::from("arrow keys = select, 'd' = drop, 'w' = wear/wield, 'e' = eat,".dark_gray())
-                .render(Rect::new(0, reminder_y, 64, 1), buf);
-            Line::from("'v'/'t' = evoke/throw, esc = close".dark_gray())
-                .render(Rect::new(0, reminder_y + 1, 64, 1), buf);
+            Line::from("letter/enter = select, arrow keys = move, esc = close".dark_gray())
+                .render(reminder_area, buf);
+        } else if self.look_cursor.is_some() {
+            Line::from("arrow keys = move cursor, esc = stop looking".dark_gray())
+                .render(reminder_area, buf);
+        } else if self.character_sheet_open {
+            Line::from("esc = close".dark_gray())
+                .render(reminder_area, buf);
         } else {
-            Line::from("arrow keys = move, '.' = wait, 'g' = pick up, 'i' = inventory".dark_gray())
-                .render(Rect::new(0, reminder_y, 64, 1), buf);
+            Line::from("arrow keys = move, '.' = wait, 'g' = pick up, 'i' = inventory,".dark_gray())
+                .render(reminder_area, buf);
+            Line::from("'r' = rest, 'x' = look, 'c' = character".dark_gray())
+                .render(second_reminder_area, buf);
         }
     }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+    for error in prefab::load_prefabs() {
+        eprintln!("failed to load prefab {}", error);
+    }
     let use_test_level = env::args().any(|arg| arg == "--test-level");
-    let mut app = TerminalApp::new(use_test_level);
+    let hunger_enabled = env::args().any(|arg| arg == "--hunger");
+    let animations_enabled = !env::args().any(|arg| arg == "--no-animations");
+    // E.g. --auto-pickup=consumables,wands
+    let mut auto_pickup = HashSet::new();
+    for arg in env::args() {
+        let Some(category_names) = arg.strip_prefix("--auto-pickup=") else {
+            continue;
+        };
+        for name in category_names.split(',') {
+            match ALL_ITEM_CATEGORIES.iter().find(|&&category| strings::item_category_to_name(category).eq_ignore_ascii_case(name)) {
+                Some(&category) => { auto_pickup.insert(category); },
+                // Fail before the terminal takes over the screen, where the message would be lost
+                None => return Err(eyre!("unknown item category {}", name)),
+            }
+        }
+    }
+    let mut app = TerminalApp::new(use_test_level, hunger_enabled, auto_pickup, animations_enabled);
     let mut terminal = ratatui::init();
     let app_result = app.run(&mut terminal);
     ratatui::restore();
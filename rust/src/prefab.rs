@@ -0,0 +1,251 @@
+// Hand-authored vaults stamped into generated rooms. See prefabs/README.md for the file format.
+
+use std::collections::HashSet;
+use std::fs;
+use std::sync::OnceLock;
+
+use cgmath::vec2;
+
+use crate::data::{ALL_ACTOR_TYPES, ALL_ITEM_TYPES, ALL_MISC_ENTITY_TYPES, CellType, NEIGHBORS, TilePoint, TileSize};
+use crate::generate::GeneratedCell;
+use crate::strings;
+
+pub const PREFAB_DIR: &str = "prefabs";
+
+const BUILTIN_PREFABS: &[(&str, &str)] = &[
+    ("healing_font.txt", include_str!("../prefabs/healing_font.txt")),
+    ("thistle_cache.txt", include_str!("../prefabs/thistle_cache.txt")),
+    ("skeleton_crypt.txt", include_str!("../prefabs/skeleton_crypt.txt")),
+];
+
+static PREFABS: OnceLock<Vec<Prefab>> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct Prefab {
+    #[allow(unused)]
+    pub name: String,
+    pub min_depth: i32,
+    pub size: TileSize,
+    // Indexed [x][y] like rooms. None means the prefab leaves that cell to the generator.
+    pub cells: Vec<Vec<Option<GeneratedCell>>>,
+}
+
+fn parse_terrain(c: char) -> Option<CellType> {
+    match c {
+        '#' => Some(CellType::DefaultWall),
+        '.' => Some(CellType::DefaultFloor),
+        ':' => Some(CellType::FloorMoss),
+        '"' => Some(CellType::FloorThyme),
+        '~' => Some(CellType::Water),
+        _ => None,
+    }
+}
+
+const UNTOUCHED_CHAR: char = '?';
+
+fn parse_legend_entry(value: &str) -> Result<(char, GeneratedCell), String> {
+    let (glyph, definition) = value.split_once('=').ok_or(format!("legend entry \"{}\" is missing '='", value))?;
+    let glyph = glyph.trim();
+    let mut glyph_chars = glyph.chars();
+    let c = match (glyph_chars.next(), glyph_chars.next()) {
+        (Some(c), None) => c,
+        _ => return Err(format!("legend glyph \"{}\" must be a single character", glyph)),
+    };
+    if parse_terrain(c).is_some() || c == UNTOUCHED_CHAR {
+        return Err(format!("legend glyph '{}' is reserved for terrain", c));
+    }
+
+    let (kind, name) = definition.trim().split_once(' ').ok_or(format!("legend entry \"{}\" needs a kind and a name", value))?;
+    let name = name.trim();
+    let mut cell = GeneratedCell {
+        cell_type: CellType::DefaultFloor,
+        immutable: true,
+        ..Default::default()
+    };
+    match kind {
+        "monster" => {
+            cell.monster = Some(*ALL_ACTOR_TYPES.iter().find(|&&t| strings::actor_type_to_name(t) == name)
+                .ok_or(format!("unknown monster \"{}\"", name))?);
+        },
+        "item" => {
+            cell.item = Some(*ALL_ITEM_TYPES.iter().find(|&&t| strings::item_type_to_name(t) == name)
+                .ok_or(format!("unknown item \"{}\"", name))?);
+        },
+        "entity" => {
+            cell.misc_entity = Some(*ALL_MISC_ENTITY_TYPES.iter().find(|&&t| strings::misc_entity_type_to_name(t) == name)
+                .ok_or(format!("unknown entity \"{}\"", name))?);
+        },
+        _ => return Err(format!("unknown legend kind \"{}\", expected monster, item or entity", kind)),
+    }
+    Ok((c, cell))
+}
+
+// Every open cell must be reachable from an opening on the prefab's edge, otherwise stamping it could cut
+// off part of the room. Untouched cells count as open since the generator may carve them.
+fn check_prefab_connectivity(size: TileSize, cells: &[Vec<Option<GeneratedCell>>]) -> Result<(), String> {
+    let is_passable = |p: TilePoint| match &cells[p.x as usize][p.y as usize] {
+        Some(cell) => cell.cell_type != CellType::DefaultWall,
+        None => true,
+    };
+    let mut reached = HashSet::new();
+    let mut pending = vec![];
+    for x in 0..size.x { for y in 0..size.y {
+        let p = vec2(x as i32, y as i32);
+        let on_edge = x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1;
+        if on_edge && is_passable(p) {
+            reached.insert(p);
+            pending.push(p);
+        }
+    }}
+    if pending.is_empty() {
+        return Err("prefab has no opening on its edge".to_string());
+    }
+    while let Some(current) = pending.pop() {
+        for &(dx, dy) in NEIGHBORS.iter() {
+            let adj = vec2(current.x + dx, current.y + dy);
+            if adj.x < 0 || adj.y < 0 || adj.x as usize >= size.x || adj.y as usize >= size.y {
+                continue;
+            }
+            if is_passable(adj) && reached.insert(adj) {
+                pending.push(adj);
+            }
+        }
+    }
+    for x in 0..size.x { for y in 0..size.y {
+        let p = vec2(x as i32, y as i32);
+        if is_passable(p) && !reached.contains(&p) {
+            return Err(format!("cell ({}, {}) can't be reached from the prefab's openings", x, y));
+        }
+    }}
+    Ok(())
+}
+
+pub fn parse_prefab(text: &str) -> Result<Prefab, String> {
+    let mut name = None;
+    let mut min_depth = 0;
+    let mut legend = vec![];
+    let mut lines = text.lines();
+    loop {
+        let line = lines.next().ok_or("missing \"---\" line before the grid")?;
+        let line = line.trim();
+        if line == "---" {
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once(':').ok_or(format!("expected \"key: value\", got \"{}\"", line))?;
+        match key.trim() {
+            "name" => name = Some(value.trim().to_string()),
+            "min_depth" => min_depth = value.trim().parse::<i32>().map_err(|_| format!("bad min_depth \"{}\"", value.trim()))?,
+            "legend" => legend.push(parse_legend_entry(value)?),
+            other => return Err(format!("unknown key \"{}\"", other)),
+        }
+    }
+    let name = name.ok_or("missing name")?;
+
+    let rows: Vec<&str> = lines.map(|line| line.trim_end()).filter(|line| !line.is_empty()).collect();
+    if rows.is_empty() {
+        return Err("empty grid".to_string());
+    }
+    let width = rows[0].chars().count();
+    if rows.iter().any(|row| row.chars().count() != width) {
+        return Err("grid rows must all be the same width".to_string());
+    }
+    let size = vec2(width, rows.len());
+
+    let mut cells = vec![ vec![ None ; size.y ] ; size.x ];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            cells[x][y] = if c == UNTOUCHED_CHAR {
+                None
+            } else if let Some(cell_type) = parse_terrain(c) {
+                Some(GeneratedCell { cell_type, immutable: true, ..Default::default() })
+            } else if let Some((_, cell)) = legend.iter().find(|(glyph, _)| *glyph == c) {
+                Some(cell.clone())
+            } else {
+                return Err(format!("unknown glyph '{}' at ({}, {})", c, x, y));
+            };
+        }
+    }
+    check_prefab_connectivity(size, &cells)?;
+
+    Ok(Prefab { name, min_depth, size, cells })
+}
+
+fn parse_builtin_prefabs() -> Vec<Prefab> {
+    BUILTIN_PREFABS.iter()
+        .map(|(file_name, text)| parse_prefab(text).unwrap_or_else(|e| panic!("built-in prefab {}: {}", file_name, e)))
+        .collect()
+}
+
+// Loads prefabs from PREFAB_DIR if it exists, otherwise uses the built-in copies. Must be called before the
+// first room is generated to take effect. Returns a message for each file that failed to load.
+pub fn load_prefabs() -> Vec<String> {
+    let mut errors = vec![];
+    let prefabs = match fs::read_dir(PREFAB_DIR) {
+        Ok(entries) => {
+            let mut paths: Vec<_> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                .collect();
+            paths.sort();
+            let mut prefabs = vec![];
+            for path in paths {
+                let result = fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| parse_prefab(&text));
+                match result {
+                    Ok(prefab) => prefabs.push(prefab),
+                    Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+            prefabs
+        },
+        Err(_) => parse_builtin_prefabs(),
+    };
+    let _ = PREFABS.set(prefabs);
+    errors
+}
+
+pub fn get_prefabs() -> &'static [Prefab] {
+    PREFABS.get_or_init(parse_builtin_prefabs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ActorType, ItemType, MiscEntityType};
+
+    #[test]
+    fn test_builtin_prefabs_parse() {
+        assert_eq!(BUILTIN_PREFABS.len(), parse_builtin_prefabs().len());
+    }
+
+    #[test]
+    fn test_parse_prefab() {
+        let prefab = parse_prefab("name: test\nmin_depth: 3\nlegend: t = monster mortal toad\nlegend: * = item lump of blackstone\nlegend: + = entity steel thistle\n---\n?###\n.t*#\n?+##\n").unwrap();
+        assert_eq!("test", prefab.name);
+        assert_eq!(3, prefab.min_depth);
+        assert_eq!(vec2(4, 3), prefab.size);
+        assert!(prefab.cells[0][0].is_none());
+        let wall = prefab.cells[1][0].as_ref().unwrap();
+        assert_eq!(CellType::DefaultWall, wall.cell_type);
+        assert!(wall.immutable);
+        let toad = prefab.cells[1][1].as_ref().unwrap();
+        assert_eq!(CellType::DefaultFloor, toad.cell_type);
+        assert_eq!(Some(ActorType::Toad), toad.monster);
+        assert_eq!(Some(ItemType::LumpOfBlackstone), prefab.cells[2][1].as_ref().unwrap().item);
+        assert_eq!(Some(MiscEntityType::SteelThistle), prefab.cells[1][2].as_ref().unwrap().misc_entity);
+    }
+
+    #[test]
+    fn test_parse_prefab_errors() {
+        assert!(parse_prefab("name: test\n#.#\n").is_err());
+        assert!(parse_prefab("---\n#.#\n").is_err());
+        assert!(parse_prefab("name: test\n---\n#.#\n#.\n").is_err());
+        assert!(parse_prefab("name: test\n---\n#x#\n").is_err());
+        assert!(parse_prefab("name: test\nlegend: x = monster dragon\n---\n#x.\n").is_err());
+        assert!(parse_prefab("name: test\nlegend: # = item wand of ice\n---\n#.#\n").is_err());
+        // Closed off rooms would break connectivity
+        assert!(parse_prefab("name: test\n---\n###\n#.#\n###\n").is_err());
+    }
+}
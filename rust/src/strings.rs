@@ -1,17 +1,23 @@
 use std::collections::HashMap;
 
-use crate::data::{ActorType, ItemType, GameEvent, EquipSlot, get_item_data};
+use crate::data::{ActorType, Awareness, CellType, ItemCategory, ItemType, MiscEntityType, GameEvent, EquipSlot, HungerState, SpeedCondition, get_item_data, get_terrain_data};
 
 pub const EMPTY_INVENTORY: &str = "nothing is being carried";
 
 pub const DIRECTION_SELECTION_PROMPT: &str = "In which direction?";
 
+pub const UNEXPLORED_LOOK_TEXT: &str = "unexplored";
+
+pub const NOTHING_EQUIPPED: &str = "nothing";
+
+pub const TERMINAL_TOO_SMALL: &str = "The terminal is too small to play.";
+
 pub enum NamedType {
     ActorType { actor_type: ActorType },
-    ItemType { item_type: ItemType },
+    ItemType { item_type: ItemType, quantity: i32 },
 }
 
-fn actor_type_to_name(actor_type: ActorType) -> &'static str {
+pub fn actor_type_to_name(actor_type: ActorType) -> &'static str {
     match actor_type {
         ActorType::Player => "((player))",
         ActorType::Toad => "mortal toad",
@@ -20,12 +26,18 @@ fn actor_type_to_name(actor_type: ActorType) -> &'static str {
         ActorType::ToothyStarling => "toothy starling",
         ActorType::DustySkeleton => "dusty skeleton",
         ActorType::BlueJelly => "blue jelly",
+        ActorType::BloodflowerBush => "bloodflower bush",
+        ActorType::BerryShrub => "darkberry shrub",
+        ActorType::Heron => "heron",
+        ActorType::StarlightFairy => "starlight fairy",
+        ActorType::Mermaid => "mermaid",
     }
 }
 
 pub fn item_type_to_name(item_type: ItemType) -> &'static str {
     match item_type {
         ItemType::LumpOfBlackstone => "lump of blackstone",
+        ItemType::Javelin => "javelin",
         ItemType::BlackstoneSpear => "blackstone spear",
         ItemType::CarmineSword => "carmine sword",
         ItemType::MoonlightKnife => "moonlight knife",
@@ -36,11 +48,127 @@ pub fn item_type_to_name(item_type: ItemType) -> &'static str {
         ItemType::Bloodflower => "bloodflower",
         ItemType::ElephantFrond => "elephant frond",
         ItemType::ArmadilloFlower => "armadillo flower",
+        ItemType::Darkberry => "darkberry",
+        ItemType::Acorn => "acorn",
+        ItemType::Carcass => "carcass",
         ItemType::WandOfIce => "wand of ice",
     }
 }
 
-fn get_actor_name<'a, 'b>(actor_id: u32, player_name: &'a str, type_table: &'b HashMap<u32, NamedType>) -> &'a str {
+pub fn item_type_to_plural_name(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::LumpOfBlackstone => "lumps of blackstone",
+        ItemType::Javelin => "javelins",
+        ItemType::BlackstoneSpear => "blackstone spears",
+        ItemType::CarmineSword => "carmine swords",
+        ItemType::MoonlightKnife => "moonlight knives",
+        ItemType::BoneLamellar => "bone lamellars",
+        ItemType::FeatheredCavalier => "feathered cavaliers",
+        ItemType::CarmineHelm => "carmine helms",
+        ItemType::CarmineChainmail => "carmine chainmails",
+        ItemType::Bloodflower => "bloodflowers",
+        ItemType::ElephantFrond => "elephant fronds",
+        ItemType::ArmadilloFlower => "armadillo flowers",
+        ItemType::Darkberry => "darkberries",
+        ItemType::Acorn => "acorns",
+        ItemType::Carcass => "carcasses",
+        ItemType::WandOfIce => "wands of ice",
+    }
+}
+
+pub fn item_type_to_description(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::LumpOfBlackstone => "A heavy black rock that fits nicely in the hand. It shatters when thrown.",
+        ItemType::Javelin => "A light spear balanced for throwing. It can be picked up again after it lands.",
+        ItemType::BlackstoneSpear => "A long spear tipped with knapped blackstone.",
+        ItemType::CarmineSword => "A heavy red blade. It hits hard, but takes a while to swing.",
+        ItemType::MoonlightKnife => "A pale little knife, good for harvesting plants cleanly.",
+        ItemType::BoneLamellar => "Armor made of small bones laced together.",
+        ItemType::FeatheredCavalier => "A wide hat with a long feather. Wearing it is invigorating.",
+        ItemType::CarmineHelm => "A sturdy red helm.",
+        ItemType::CarmineChainmail => "A shirt of fine red rings.",
+        ItemType::Bloodflower => "A deep red flower. Eating it closes wounds.",
+        ItemType::ElephantFrond => "A broad, tough leaf. Eating it makes you permanently stronger.",
+        ItemType::ArmadilloFlower => "A flower with armored petals. Eating it makes you permanently tougher.",
+        ItemType::Darkberry => "A small, dark berry that heals a little.",
+        ItemType::Acorn => "A filling nut.",
+        ItemType::Carcass => "The remains of a monster. Not appetizing, but very filling.",
+        ItemType::WandOfIce => "Fires a freezing beam that slows whatever it hits and freezes water.",
+    }
+}
+
+pub fn item_category_to_name(category: ItemCategory) -> &'static str {
+    match category {
+        ItemCategory::Weapon => "Weapons",
+        ItemCategory::Armor => "Armor",
+        ItemCategory::Consumable => "Consumables",
+        ItemCategory::Wand => "Wands",
+    }
+}
+
+pub fn misc_entity_type_to_name(entity_type: MiscEntityType) -> &'static str {
+    match entity_type {
+        MiscEntityType::SteelThistle => "steel thistle",
+        MiscEntityType::TreasureChest => "treasure chest",
+        MiscEntityType::HealingFont => "healing font",
+    }
+}
+
+fn cell_type_to_name(cell_type: CellType) -> &'static str {
+    match cell_type {
+        CellType::OutOfBounds | CellType::Empty => "nothing",
+        CellType::DefaultFloor => "floor",
+        CellType::FloorMoss => "moss",
+        CellType::FloorThyme => "thyme",
+        CellType::DefaultWall => "wall",
+        CellType::RoomExit => "exit",
+        CellType::Water => "water",
+        CellType::Ice => "ice",
+    }
+}
+
+pub fn awareness_to_name(awareness: Awareness) -> &'static str {
+    match awareness {
+        Awareness::Asleep => "asleep",
+        Awareness::Wandering => "wandering",
+        Awareness::Hunting => "hunting",
+        Awareness::Searching => "searching",
+        Awareness::Fleeing => "fleeing",
+    }
+}
+
+pub fn hunger_state_to_name(hunger: HungerState) -> &'static str {
+    match hunger {
+        HungerState::Fed => "fed",
+        HungerState::Hungry => "hungry",
+        HungerState::Starving => "starving",
+    }
+}
+
+pub fn equip_slot_to_name(slot: EquipSlot) -> &'static str {
+    match slot {
+        EquipSlot::Weapon => "weapon",
+        EquipSlot::Headgear => "head",
+        EquipSlot::Torso => "body",
+    }
+}
+
+pub fn speed_condition_to_name(condition: SpeedCondition) -> &'static str {
+    match condition {
+        SpeedCondition::Slowed => "slowed",
+    }
+}
+
+pub fn get_terrain_description(cell_type: CellType) -> String {
+    let effects = get_terrain_data(cell_type).effects;
+    if effects.is_empty() {
+        cell_type_to_name(cell_type).to_string()
+    } else {
+        format!("{} ({})", cell_type_to_name(cell_type), effects.join(", "))
+    }
+}
+
+fn get_actor_name<'a>(actor_id: u32, player_name: &'a str, type_table: &HashMap<u32, NamedType>) -> &'a str {
     match type_table.get(&actor_id) {
         Some(NamedType::ActorType { actor_type: ActorType::Player }) => player_name,
         Some(NamedType::ActorType { actor_type }) => actor_type_to_name(*actor_type),
@@ -49,18 +177,26 @@ fn get_actor_name<'a, 'b>(actor_id: u32, player_name: &'a str, type_table: &'b H
     }
 }
 
-pub fn get_item_name(item_id: u32, type_table: &HashMap<u32, NamedType>) -> &'static str {
+pub fn get_item_name(item_id: u32, type_table: &HashMap<u32, NamedType>) -> String {
+    match type_table.get(&item_id) {
+        Some(NamedType::ItemType { item_type, quantity: 1 }) => item_type_to_name(*item_type).to_string(),
+        Some(NamedType::ItemType { item_type, quantity }) => format!("{} {}", quantity, item_type_to_plural_name(*item_type)),
+        Some(NamedType::ActorType { .. }) => "((actor instead of item))".to_string(),
+        None => "((unknown item))".to_string(),
+    }
+}
+
+fn get_is_or_are(item_id: u32, type_table: &HashMap<u32, NamedType>) -> &'static str {
     match type_table.get(&item_id) {
-        Some(NamedType::ItemType { item_type }) => item_type_to_name(*item_type),
-        Some(NamedType::ActorType { .. }) => "((actor instead of item))",
-        None => "((unknown item))",
+        Some(NamedType::ItemType { quantity, .. }) if *quantity > 1 => "are",
+        _ => "is",
     }
 }
 
 pub fn get_equipped_past_verb(item_id: u32, type_table: &HashMap<u32, NamedType>) -> &'static str {
     let item_type = type_table.get(&item_id);
     match item_type {
-        Some(NamedType::ItemType { item_type }) => {
+        Some(NamedType::ItemType { item_type, .. }) => {
             let equip_slot = get_item_data(*item_type).equip_slot;
             match equip_slot {
                 EquipSlot::Weapon => "wielded",
@@ -75,7 +211,7 @@ pub fn get_equipped_past_verb(item_id: u32, type_table: &HashMap<u32, NamedType>
 pub fn get_equipped_participle(item_id: u32, type_table: &HashMap<u32, NamedType>) -> &'static str {
     let item_type = type_table.get(&item_id);
     match item_type {
-        Some(NamedType::ItemType { item_type }) => {
+        Some(NamedType::ItemType { item_type, .. }) => {
             let equip_slot = get_item_data(*item_type).equip_slot;
             match equip_slot {
                 EquipSlot::Weapon => "wielded",
@@ -89,7 +225,7 @@ pub fn get_equipped_participle(item_id: u32, type_table: &HashMap<u32, NamedType
 fn get_unequipped_past_verb(item_id: u32, type_table: &HashMap<u32, NamedType>) -> &'static str {
     let item_type = type_table.get(&item_id);
     match item_type {
-        Some(NamedType::ItemType { item_type }) => {
+        Some(NamedType::ItemType { item_type, .. }) => {
             let equip_slot = get_item_data(*item_type).equip_slot;
             match equip_slot {
                 EquipSlot::Weapon => "put away",
@@ -103,7 +239,9 @@ fn get_unequipped_past_verb(item_id: u32, type_table: &HashMap<u32, NamedType>)
 
 fn get_activated_past_verb(item_id: u32, type_table: &HashMap<u32, NamedType>) -> &'static str {
     match type_table.get(&item_id) {
-        Some(NamedType::ItemType { item_type: ItemType::LumpOfBlackstone }) => "threw",
+        Some(NamedType::ItemType { item_type: ItemType::LumpOfBlackstone, .. }) => "threw",
+        Some(NamedType::ItemType { item_type: ItemType::Javelin, .. }) => "threw",
+        Some(NamedType::ItemType { item_type: ItemType::MoonlightKnife, .. }) => "swung",
         _ => "invoked",
     }
 }
@@ -123,6 +261,9 @@ pub fn get_string(event: GameEvent, player_name: &str, type_table: &HashMap<u32,
         GameEvent::ItemNotEdible { item_id } => format!("chewed on {}, ineffectually", get_item_name(item_id, type_table)),
         GameEvent::EffectHealed { actor_id } => format!("{} is healed", get_actor_name(actor_id, player_name, type_table)),
         GameEvent::SlowedByWater { actor_id } => format!("{} is slowed wading in the water", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::SlidOnIce { actor_id } => format!("{} slides across the ice", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::WaterFroze => "the water freezes solid".to_string(),
+        GameEvent::IceMelted => "the ice melts".to_string(),
         GameEvent::ActivatedItem { item_id } => format!("{} {}", get_activated_past_verb(item_id, type_table), get_item_name(item_id, type_table)),
         GameEvent::EffectIceDamage { actor_id, damage } => format!("\u{2744}{} {}", damage, get_actor_name(actor_id, player_name, type_table)),
         GameEvent::NoEffect { item_id } => format!("{} has no effect", get_item_name(item_id, type_table)),
@@ -130,10 +271,35 @@ pub fn get_string(event: GameEvent, player_name: &str, type_table: &HashMap<u32,
         GameEvent::ThrownStoneDamage { actor_id, damage } => format!("\u{25cf}{} {}", damage, get_actor_name(actor_id, player_name, type_table)),
         GameEvent::JavelinDamage { actor_id, damage } => format!("\u{2191}{} {}", damage, get_actor_name(actor_id, player_name, type_table)),
         GameEvent::WandExpended { item_id } => format!("{} turns to dust", get_item_name(item_id, type_table)),
-        GameEvent::ItemIsHere { item_id } => format!("{} is here", get_item_name(item_id, type_table)),
+        GameEvent::ItemIsHere { item_id } => format!("{} {} here", get_item_name(item_id, type_table), get_is_or_are(item_id, type_table)),
         GameEvent::EffectStronger { actor_id } => format!("{} feels stronger", get_actor_name(actor_id, player_name, type_table)),
         GameEvent::EffectTougher { actor_id } => format!("{} feels tougher", get_actor_name(actor_id, player_name, type_table)),
         GameEvent::UsedHealingFont { actor_id } => format!("{} is washed by healing waters", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::Harvested { actor_id, item_id } => format!("harvested {} from {}", get_item_name(item_id, type_table), get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::NothingToHarvest { actor_id } => format!("{} has nothing left to harvest", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::LevelUp { actor_id, level } => format!("{} reached level {}", get_actor_name(actor_id, player_name, type_table), level),
+        GameEvent::Rested { turns } => format!("rested for {} turn{}", turns, if turns == 1 { "" } else { "s" }),
+        GameEvent::CantRestNearMonsters => "can't rest with monsters in view".to_string(),
+        GameEvent::HungerChanged { actor_id, hunger } => match hunger {
+            HungerState::Fed => format!("{} is no longer hungry", get_actor_name(actor_id, player_name, type_table)),
+            _ => format!("{} is {}", get_actor_name(actor_id, player_name, type_table), hunger_state_to_name(hunger)),
+        },
+        GameEvent::StarvationDamage { actor_id, damage } => format!("{} starves for {} damage", get_actor_name(actor_id, player_name, type_table), damage),
+        GameEvent::DroppedLoot { actor_id, item_id } => format!("{} drops {}", get_actor_name(actor_id, player_name, type_table), get_item_name(item_id, type_table)),
+        GameEvent::PickedUpItem { actor_id, item_id } => format!("{} picks up {}", get_actor_name(actor_id, player_name, type_table), get_item_name(item_id, type_table)),
+        GameEvent::Dazzled { attacker_id, defender_id } => {
+            format!("{} dazzles {} with fairy lights", get_actor_name(attacker_id, player_name, type_table), get_actor_name(defender_id, player_name, type_table))
+        },
+        GameEvent::DazzleMiss { attacker_id, defender_id } => {
+            format!("{} is distracted by fairy lights and misses {}", get_actor_name(attacker_id, player_name, type_table), get_actor_name(defender_id, player_name, type_table))
+        },
+        GameEvent::DazzleFaded { actor_id } => format!("the fairy lights fade away from {}", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::MonsterFled { actor_id } => format!("{} breaks and flees", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::MonsterRallied { actor_id } => format!("{} rallies", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::EffectSlowed { actor_id } => format!("{} is slowed by the cold", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::SpeedReturnedToNormal { actor_id } => format!("{} moves at normal speed again", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::NoticedPlayer { actor_id } => format!("{} notices you", get_actor_name(actor_id, player_name, type_table)),
+        GameEvent::WokeUp { actor_id } => format!("{} wakes up", get_actor_name(actor_id, player_name, type_table)),
         GameEvent::Winner => "winner [end of playtest]".to_string(),
     }
 }
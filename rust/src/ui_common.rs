@@ -1,6 +1,12 @@
+use std::collections::HashSet;
+
+use crate::data::{ItemCategory, ItemType, ProjectileLanding, get_item_data, get_projectile_data};
+
 pub struct ItemMenu {
     pub item_ids: Vec<u32>,
     pub cursor_index: usize,
+    // For menus where several items can be chosen at once
+    pub selected: HashSet<u32>,
 }
 
 impl ItemMenu {
@@ -8,6 +14,7 @@ impl ItemMenu {
         ItemMenu {
             item_ids,
             cursor_index: 0,
+            selected: HashSet::new(),
         }
     }
 
@@ -28,4 +35,59 @@ impl ItemMenu {
         }
         self.cursor_index = new_index.try_into().unwrap();
     }
+
+    pub fn toggle_selected(&mut self, item_id: u32) {
+        if !self.selected.remove(&item_id) {
+            self.selected.insert(item_id);
+        }
+    }
+
+    pub fn move_cursor_to(&mut self, item_id: u32) {
+        if let Some(index) = self.item_ids.iter().position(|&id| id == item_id) {
+            self.cursor_index = index;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ItemAction {
+    Equip,
+    Eat,
+    Throw,
+    Evoke,
+    Drop,
+    DropOne,
+}
+
+// Only what makes sense for the item, so eating a sword is never offered
+pub fn get_item_actions(item_type: ItemType, quantity: i32) -> Vec<ItemAction> {
+    let mut actions = vec![];
+    let category = get_item_data(item_type).category;
+    if category == ItemCategory::Weapon || category == ItemCategory::Armor {
+        actions.push(ItemAction::Equip);
+    }
+    if category == ItemCategory::Consumable {
+        actions.push(ItemAction::Eat);
+    }
+    if get_projectile_data(item_type).is_some_and(|projectile| projectile.landing != ProjectileLanding::Dissipates) {
+        actions.push(ItemAction::Throw);
+    }
+    // The knife is evoked to harvest plants
+    if category == ItemCategory::Wand || item_type == ItemType::MoonlightKnife {
+        actions.push(ItemAction::Evoke);
+    }
+    actions.push(ItemAction::Drop);
+    if quantity > 1 {
+        actions.push(ItemAction::DropOne);
+    }
+    actions
+}
+
+// First map coordinate to show along one axis so the focus stays centered, without scrolling past the map
+// edges. Maps that fit in the view are never scrolled.
+pub fn scroll_offset(view_len: i32, map_len: i32, focus: i32) -> i32 {
+    if map_len <= view_len {
+        return 0;
+    }
+    (focus - view_len / 2).clamp(0, map_len - view_len)
 }